@@ -0,0 +1,86 @@
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+/// Env var listing Basic Auth accounts, expanded into the Caddy config at launch.
+pub(crate) const BASIC_AUTH_USERS_ENV: &str = "WEB_BASIC_AUTH_USERS";
+
+/// A Caddy `http_basic` account, with a bcrypt hashed password.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub(crate) struct BasicAuthAccount {
+    pub(crate) username: String,
+    pub(crate) password: String,
+}
+
+/// Reads accounts from an htpasswd-style file, one `username:bcrypt-hash` per line.
+pub(crate) fn read_basic_auth_users_file(path: &Path) -> Result<Vec<BasicAuthAccount>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    parse_basic_auth_users(&contents).map_err(|e| format!("{} {e}", path.display()))
+}
+
+// Blank lines & `#` comments are skipped. Caddy only verifies bcrypt hashes, like those
+// generated by `htpasswd -B`.
+fn parse_basic_auth_users(contents: &str) -> Result<Vec<BasicAuthAccount>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| match line.split_once(':') {
+            Some((username, password)) if !username.is_empty() && password.starts_with("$2") => {
+                Ok(BasicAuthAccount {
+                    username: username.to_string(),
+                    password: password.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "line {line_number} must be a username & bcrypt password hash, like username:$2y$10$…"
+            )),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_basic_auth_users() {
+        let accounts = parse_basic_auth_users(
+            "# Staging reviewers\n\
+            visitor:$2y$10$Uc4licEvYDo2DqtNnTkfV.o0Bvr4Sqw0Vpdh7UMrmkqwTZ92EMkZ6\n\
+            \n\
+            reviewer:$2b$10$Uc4licEvYDo2DqtNnTkfV.o0Bvr4Sqw0Vpdh7UMrmkqwTZ92EMkZ6\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            accounts,
+            vec![
+                BasicAuthAccount {
+                    username: "visitor".to_string(),
+                    password: "$2y$10$Uc4licEvYDo2DqtNnTkfV.o0Bvr4Sqw0Vpdh7UMrmkqwTZ92EMkZ6"
+                        .to_string(),
+                },
+                BasicAuthAccount {
+                    username: "reviewer".to_string(),
+                    password: "$2b$10$Uc4licEvYDo2DqtNnTkfV.o0Bvr4Sqw0Vpdh7UMrmkqwTZ92EMkZ6"
+                        .to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_basic_auth_users_errors() {
+        assert_eq!(
+            parse_basic_auth_users("visitor:$2y$10$abc\nreviewer:plaintext\n"),
+            Err(
+                "line 2 must be a username & bcrypt password hash, like username:$2y$10$…"
+                    .to_string()
+            )
+        );
+        assert!(parse_basic_auth_users(":$2y$10$abc").is_err());
+    }
+}
@@ -0,0 +1,39 @@
+#![allow(unused_crate_dependencies)]
+use std::env;
+
+use libcnb::data::exec_d_program_output_key;
+use libcnb::exec_d::write_exec_d_program_output;
+
+// Matches the cost of hashes generated by `htpasswd -B`, because Caddy verifies the password
+// against the hash for every request.
+const BCRYPT_COST: u32 = 10;
+
+// Exports the bcrypt hash of a plaintext password, so that the password is never written to disk.
+// Runs before caddy-config-env, which keeps the Basic Auth account only when the hash is set,
+// because exec.d programs run in lexical order, each with the env exported by those before it.
+fn main() {
+    let Some(password) = env::var("WEB_BASIC_AUTH_PASSWORD")
+        .ok()
+        .filter(|v| !v.is_empty())
+    else {
+        return;
+    };
+
+    if env::var("WEB_BASIC_AUTH_PASSWORD_BCRYPT").is_ok_and(|v| !v.is_empty()) {
+        eprintln!("Basic Auth password hash replacing WEB_BASIC_AUTH_PASSWORD_BCRYPT with the hash of WEB_BASIC_AUTH_PASSWORD.");
+    }
+
+    match bcrypt::hash(password, BCRYPT_COST) {
+        Ok(password_hash) => {
+            write_exec_d_program_output([(
+                exec_d_program_output_key!("WEB_BASIC_AUTH_PASSWORD_BCRYPT"),
+                password_hash,
+            )]);
+            eprintln!("Basic Auth password hash set from WEB_BASIC_AUTH_PASSWORD");
+        }
+        Err(e) => {
+            eprintln!("Basic Auth password hash failed for WEB_BASIC_AUTH_PASSWORD: {e}");
+            std::process::exit(1);
+        }
+    }
+}
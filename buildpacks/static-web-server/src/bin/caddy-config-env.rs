@@ -0,0 +1,475 @@
+#![allow(unused_crate_dependencies)]
+use std::{env, fs};
+
+use serde_json::{json, Map, Value};
+use static_web_server_utils::is_ip_range;
+
+fn main() {
+    // Expects CADDY_CONFIG_ENV_FILE to be set internally during CNB build, in config_web_server.
+    let Ok(config_path) = env::var("CADDY_CONFIG_ENV_FILE") else {
+        eprintln!("Caddy config from env skipped, because no config file is set.");
+        std::process::exit(0);
+    };
+
+    let mut config: Value = match fs::read(&config_path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+    {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Caddy config from env failed to read '{config_path}': {e}");
+            std::process::exit(1);
+        }
+    };
+
+    match expand_env(&mut config, &|name| env::var(name).ok()) {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            eprintln!("Caddy config from env failed: {e}");
+            std::process::exit(1);
+        }
+    }
+
+    let written = serde_json::to_vec(&config)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| fs::write(&config_path, bytes).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        eprintln!("Caddy config from env failed to write '{config_path}': {e}");
+        std::process::exit(1);
+    }
+    eprintln!("Caddy config from env written into '{config_path}'");
+}
+
+// Expands values from env vars, that Caddy cannot expand itself, in IP `ranges`, Basic Auth
+// `accounts`, the forward auth URL, and proxy upstream origins. Items are listed in env vars
+// separated by commas or whitespace. An unset env var lists nothing. Returns whether anything
+// was expanded.
+fn expand_env(value: &mut Value, get_env: &dyn Fn(&str) -> Option<String>) -> Result<bool, String> {
+    let mut expanded = false;
+    match value {
+        Value::Object(map) => {
+            if let Some(url) = map.remove("forward_auth_url") {
+                expand_forward_auth_url(map, &url, get_env)?;
+                expanded = true;
+            }
+            if let Some(url) = map.remove("upstream_url") {
+                expand_upstream_url(map, &url, get_env)?;
+                expanded = true;
+            }
+            for (key, child) in map.iter_mut() {
+                match (key.as_str(), child) {
+                    ("ranges", Value::Array(ranges)) => {
+                        expanded |= expand_ranges(ranges, get_env);
+                    }
+                    ("accounts", Value::Array(accounts)) => {
+                        expanded |= expand_accounts(accounts, get_env);
+                    }
+                    (_, child) => {
+                        expanded |= expand_env(child, get_env)?;
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                expanded |= expand_env(item, get_env)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(expanded)
+}
+
+// Caddy dials upstreams by network address rather than URL, so the reverse proxy handler is
+// given the URL's upstream, with TLS for https, and the URL's path & query as the subrequest URI.
+// Requests must not be served without asking the auth service, so an unset or invalid URL fails.
+fn expand_forward_auth_url(
+    handler: &mut Map<String, Value>,
+    url: &Value,
+    get_env: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    let env_name = env_name_of(Some(url))
+        .ok_or_else(|| format!("forward_auth_url must be an env placeholder, but got {url}"))?;
+    let url = get_env(&env_name)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("forward_auth requires {env_name} to be set to the auth URL"))?;
+    let url = split_url(&url).ok_or_else(|| {
+        format!("forward_auth requires {env_name} to be a URL like https://auth.example.com/verify")
+    })?;
+
+    let mut transport = json!({ "protocol": "http" });
+    if url.tls_enabled {
+        transport["tls"] = json!({});
+    }
+    handler.insert("upstreams".to_string(), json!([{ "dial": url.dial }]));
+    handler.insert("transport".to_string(), transport);
+    handler.insert(
+        "rewrite".to_string(),
+        json!({ "method": "GET", "uri": url.uri }),
+    );
+    if let Some(Value::Object(set)) = handler
+        .get_mut("headers")
+        .and_then(|v| v.pointer_mut("/request/set"))
+    {
+        set.insert("Host".to_string(), json!([url.authority]));
+    }
+    Ok(())
+}
+
+// A proxy upstream from an env var holds a whole origin, so its scheme selects TLS, like
+// `https://api.example.com`. The proxy has nowhere to forward without it, so an unset or
+// invalid origin fails.
+fn expand_upstream_url(
+    handler: &mut Map<String, Value>,
+    url: &Value,
+    get_env: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    let env_name = env_name_of(Some(url))
+        .ok_or_else(|| format!("upstream_url must be an env placeholder, but got {url}"))?;
+    let url = get_env(&env_name)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("proxies requires {env_name} to be set to the upstream origin"))?;
+    let url = split_url(&url)
+        .filter(|url| url.uri == "/")
+        .ok_or_else(|| {
+            format!("proxies requires {env_name} to be an origin like https://api.example.com")
+        })?;
+
+    handler.insert("upstreams".to_string(), json!([{ "dial": url.dial }]));
+    if url.tls_enabled {
+        let transport = handler
+            .entry("transport")
+            .or_insert_with(|| json!({ "protocol": "http" }));
+        transport["tls"] = json!({});
+    }
+    Ok(())
+}
+
+struct SplitUrl<'a> {
+    tls_enabled: bool,
+    authority: &'a str,
+    // `host:port`, with the scheme's default port unless one is given.
+    dial: String,
+    // Path & query, at least `/`.
+    uri: String,
+}
+
+fn split_url(url: &str) -> Option<SplitUrl<'_>> {
+    let (tls_enabled, rest) = if let Some(rest) = url.strip_prefix("https://") {
+        (true, rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (false, rest)
+    } else {
+        return None;
+    };
+    let (authority, uri) = match rest.find(['/', '?']) {
+        Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+        Some(i) => (&rest[..i], rest[i..].to_string()),
+        None => (rest, "/".to_string()),
+    };
+    if authority.is_empty() || authority.contains('@') {
+        return None;
+    }
+    let has_port = match authority.rfind(']') {
+        Some(ipv6_end) => authority[ipv6_end..].contains(':'),
+        None => authority.contains(':'),
+    };
+    let dial = match (has_port, tls_enabled) {
+        (true, _) => authority.to_string(),
+        (false, true) => format!("{authority}:443"),
+        (false, false) => format!("{authority}:80"),
+    };
+    Some(SplitUrl {
+        tls_enabled,
+        authority,
+        dial,
+        uri,
+    })
+}
+
+fn env_name_of(value: Option<&Value>) -> Option<String> {
+    value
+        .and_then(Value::as_str)
+        .and_then(|v| v.strip_prefix("{env."))
+        .and_then(|v| v.strip_suffix('}'))
+        .map(String::from)
+}
+
+fn list_env(env_name: &str, get_env: &dyn Fn(&str) -> Option<String>) -> Vec<String> {
+    get_env(env_name)
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn expand_ranges(ranges: &mut Vec<Value>, get_env: &dyn Fn(&str) -> Option<String>) -> bool {
+    let mut expanded = false;
+    let mut result = vec![];
+    for range in ranges.drain(..) {
+        let Some(env_name) = env_name_of(Some(&range)) else {
+            result.push(range);
+            continue;
+        };
+        expanded = true;
+        for item in list_env(&env_name, get_env) {
+            if is_ip_range(&item) {
+                result.push(Value::String(item));
+            } else {
+                eprintln!("Caddy config from env skipping '{item}' of {env_name}, because it is not an IP address or CIDR range.");
+            }
+        }
+    }
+    *ranges = result;
+    expanded
+}
+
+// An account with an `{env.NAME}` username and no password is replaced by the accounts listed
+// in that env var, each `username:bcrypt-hash`. An account with an `{env.NAME}` username or
+// password is removed when that env var is unset, instead of failing to start Caddy.
+fn expand_accounts(accounts: &mut Vec<Value>, get_env: &dyn Fn(&str) -> Option<String>) -> bool {
+    let mut expanded = false;
+    let mut result: Vec<Value> = vec![];
+    for account in accounts.drain(..) {
+        let username_env = env_name_of(account.get("username"));
+        let password_env = env_name_of(account.get("password"));
+        let has_unset_env = [&username_env, &password_env]
+            .into_iter()
+            .flatten()
+            .any(|env_name| get_env(env_name).is_none_or(|v| v.is_empty()));
+        let listed_accounts = match (username_env, account.get("password")) {
+            (Some(env_name), None) => {
+                expanded = true;
+                list_env(&env_name, get_env)
+                    .into_iter()
+                    .filter_map(|item| match item.split_once(':') {
+                        Some((username, password))
+                            if !username.is_empty() && password.starts_with("$2") =>
+                        {
+                            Some(json!({ "username": username, "password": password }))
+                        }
+                        _ => {
+                            eprintln!("Caddy config from env skipping an account of {env_name}, because it is not a username & bcrypt password hash.");
+                            None
+                        }
+                    })
+                    .collect()
+            }
+            _ if has_unset_env => {
+                expanded = true;
+                vec![]
+            }
+            _ => vec![account],
+        };
+        for listed_account in listed_accounts {
+            if result
+                .iter()
+                .any(|v| v.get("username") == listed_account.get("username"))
+            {
+                eprintln!(
+                    "Caddy config from env skipping a duplicate account for username {}",
+                    listed_account["username"]
+                );
+            } else {
+                result.push(listed_account);
+            }
+        }
+    }
+    if expanded && result.is_empty() {
+        eprintln!("Caddy config from env found no Basic Auth accounts, so requests requiring Basic Auth will be denied.");
+    }
+    *accounts = result;
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_env_ranges() {
+        let mut config = json!({"routes":[
+            {"match":[{"client_ip":{"ranges":["10.0.0.0/8","{env.ACCESS_ALLOW_IPS}"]}}]},
+            {"match":[{"client_ip":{"ranges":["{env.ACCESS_DENY_IPS}"]}}]}
+        ]});
+        let expanded = expand_env(&mut config, &|name| match name {
+            "ACCESS_ALLOW_IPS" => {
+                Some("203.0.113.0/24, 198.51.100.7 not-an-ip,2001:db8::/32".to_string())
+            }
+            _ => None,
+        })
+        .unwrap();
+
+        assert!(expanded);
+        assert_eq!(
+            config,
+            json!({"routes":[
+                {"match":[{"client_ip":{"ranges":["10.0.0.0/8","203.0.113.0/24","198.51.100.7","2001:db8::/32"]}}]},
+                {"match":[{"client_ip":{"ranges":[]}}]}
+            ]})
+        );
+    }
+
+    #[test]
+    fn expands_env_accounts() {
+        let mut config = json!({"http_basic":{"accounts":[
+            {"username":"{env.WEB_BASIC_AUTH_USERNAME}","password":"{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"},
+            {"username":"{env.WEB_BASIC_AUTH_USERS}"},
+            {"username":"reviewer","password":"$2y$10$fromfile"}
+        ]}});
+        let expanded = expand_env(&mut config, &|name| match name {
+            "WEB_BASIC_AUTH_USERS" => {
+                Some("visitor:$2y$10$abc\nreviewer:$2y$10$def,plaintext:secret".to_string())
+            }
+            _ => None,
+        })
+        .unwrap();
+
+        assert!(expanded);
+        assert_eq!(
+            config,
+            json!({"http_basic":{"accounts":[
+                {"username":"visitor","password":"$2y$10$abc"},
+                {"username":"reviewer","password":"$2y$10$def"}
+            ]}})
+        );
+    }
+
+    #[test]
+    fn keeps_env_accounts_that_are_set() {
+        let mut config = json!({"accounts":[
+            {"username":"{env.WEB_BASIC_AUTH_USERNAME}","password":"{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"},
+            {"username":"{env.WEB_BASIC_AUTH_USERS}"}
+        ]});
+        let expanded = expand_env(&mut config, &|name| match name {
+            "WEB_BASIC_AUTH_USERNAME" => Some("visitor".to_string()),
+            "WEB_BASIC_AUTH_PASSWORD_BCRYPT" => Some("$2y$10$abc".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert!(expanded);
+        assert_eq!(
+            config,
+            json!({"accounts":[
+                {"username":"{env.WEB_BASIC_AUTH_USERNAME}","password":"{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"}
+            ]})
+        );
+    }
+
+    #[test]
+    fn expands_env_forward_auth_url() {
+        let mut config = json!({"handle":[{
+            "handler":"reverse_proxy",
+            "forward_auth_url":"{env.FORWARD_AUTH_URL}",
+            "headers":{"request":{"set":{"X-Forwarded-Uri":["{http.request.uri}"]}}}
+        }]});
+        let expanded = expand_env(&mut config, &|name| match name {
+            "FORWARD_AUTH_URL" => Some("https://auth.example.com/verify?app=docs".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert!(expanded);
+        assert_eq!(
+            config,
+            json!({"handle":[{
+                "handler":"reverse_proxy",
+                "upstreams":[{"dial":"auth.example.com:443"}],
+                "transport":{"protocol":"http","tls":{}},
+                "rewrite":{"method":"GET","uri":"/verify?app=docs"},
+                "headers":{"request":{"set":{"X-Forwarded-Uri":["{http.request.uri}"],"Host":["auth.example.com"]}}}
+            }]})
+        );
+
+        let mut config = json!({"forward_auth_url":"{env.FORWARD_AUTH_URL}"});
+        expand_env(&mut config, &|_| Some("http://127.0.0.1:8080".to_string())).unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "upstreams":[{"dial":"127.0.0.1:8080"}],
+                "transport":{"protocol":"http"},
+                "rewrite":{"method":"GET","uri":"/"}
+            })
+        );
+    }
+
+    #[test]
+    fn expands_env_forward_auth_url_errors() {
+        for url in [
+            None,
+            Some(""),
+            Some("auth.example.com/verify"),
+            Some("https:///verify"),
+        ] {
+            let mut config = json!({"forward_auth_url":"{env.FORWARD_AUTH_URL}"});
+            assert!(
+                expand_env(&mut config, &|_| url.map(String::from)).is_err(),
+                "should fail for {url:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn expands_env_upstream_url() {
+        let mut config = json!({"handle":[{
+            "handler":"reverse_proxy",
+            "upstream_url":"{env.API_ORIGIN}",
+            "transport":{"protocol":"http","dial_timeout":"5s"}
+        }]});
+        let expanded = expand_env(&mut config, &|name| match name {
+            "API_ORIGIN" => Some("https://api.example.com/".to_string()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert!(expanded);
+        assert_eq!(
+            config,
+            json!({"handle":[{
+                "handler":"reverse_proxy",
+                "upstreams":[{"dial":"api.example.com:443"}],
+                "transport":{"protocol":"http","dial_timeout":"5s","tls":{}}
+            }]})
+        );
+
+        let mut config = json!({"upstream_url":"{env.API_ORIGIN}","transport":{"protocol":"http"}});
+        expand_env(&mut config, &|_| Some("http://[::1]:8080".to_string())).unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "upstreams":[{"dial":"[::1]:8080"}],
+                "transport":{"protocol":"http"}
+            })
+        );
+    }
+
+    #[test]
+    fn expands_env_upstream_url_errors() {
+        for url in [
+            None,
+            Some(""),
+            Some("api.example.com"),
+            Some("https://api.example.com/v1"),
+            Some("https://api.example.com?v=1"),
+        ] {
+            let mut config = json!({"upstream_url":"{env.API_ORIGIN}"});
+            assert!(
+                expand_env(&mut config, &|_| url.map(String::from)).is_err(),
+                "should fail for {url:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_config_without_env_ranges() {
+        let mut config = json!({"match":[{"client_ip":{"ranges":["10.0.0.0/8"]}}]});
+        assert!(!expand_env(&mut config, &|_| None).unwrap());
+        assert_eq!(
+            config,
+            json!({"match":[{"client_ip":{"ranges":["10.0.0.0/8"]}}]})
+        );
+    }
+}
@@ -1,28 +1,97 @@
+use crate::basic_auth_users::{BasicAuthAccount, BASIC_AUTH_USERS_ENV};
+use crate::content_etags::ETAG_FILE_EXTENSION;
+use crate::csp_nonces::{NONCE_PLACEHOLDER, NONCE_TEMPLATE_DELIMITERS};
+use crate::doc_root_files::DocRootFiles;
 use crate::heroku_web_server_config::{
-    ErrorsConfig, HerokuWebServerConfig, PathMatchedHeader, DEFAULT_DOC_INDEX, DEFAULT_DOC_ROOT,
+    AccessConfig, CachingMode, CaddyBasicAuthConfig, CaddyTrustedProxiesConfig,
+    CanonicalHostConfig, ContentSecurityPolicyConfig, CorsConfig, ErrorConfig, ErrorsConfig,
+    ForceHttpsConfig, ForwardAuthConfig, HeaderMatcher, HeaderOperation, HeaderTarget,
+    HerokuWebServerConfig, MaintenanceConfig, MountConfig, PathMatchedHeader, RedirectConfig,
+    SecurityHeadersPreset, SiteConfig, SpaFallbackConfig, TrailingSlashMode, DEFAULT_DOC_INDEX,
+    DEFAULT_DOC_ROOT,
 };
+use crate::inline_hashes::InlineHashes;
 use crate::o11y::*;
 use crate::StaticWebServerBuildpackError;
 use indexmap::IndexMap;
+use regex::Regex;
 use serde::Serialize;
 use serde_json::json;
-use std::collections::HashMap;
+use static_web_server_utils::{is_env_name, is_ip_range};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+static PLACEHOLDER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{[^}]*\}").expect("placeholder regex should compile"));
+
+// References to path captures in redirect & rewrite targets, like `{1}`.
+static CAPTURE_REFERENCE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(\d+)\}").expect("capture reference regex should compile"));
+
+// HTTP tokens, such as methods, header names, and cookie names, of RFC 9110 `tchar`s.
+static HTTP_TOKEN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[A-Za-z0-9!#$%&'*+.^_`|~-]+$").expect("HTTP token regex should compile")
+});
+
+static CORS_ORIGIN_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^https?://[A-Za-z0-9.-]+(:[0-9]+)?$").expect("CORS origin regex should compile")
+});
+
+// Caddy durations, like Go's with an added `d` for days, such as `500ms` or `1m30s`.
+static DURATION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(0|([0-9]+(\.[0-9]+)?(ns|us|µs|ms|s|m|h|d))+)$")
+        .expect("duration regex should compile")
+});
+
+// Value of the maintenance bypass cookie, set at runtime, so that it is not committed to the repo.
+const MAINTENANCE_BYPASS_TOKEN_ENV: &str = "WEB_MAINTENANCE_BYPASS_TOKEN";
 
 /// Transforms the given [`HerokuWebServerConfig`] into an equivalent Caddy JSON configuration.
 /// Keeping this as a single function, because many lines are just the JSON itself being assembled.
 #[allow(clippy::too_many_lines)]
 pub(crate) fn caddy_json_config(
     config: &HerokuWebServerConfig,
+    doc_root_files: &DocRootFiles,
+    basic_auth_accounts: &[BasicAuthAccount],
 ) -> Result<serde_json::Value, StaticWebServerBuildpackError> {
     let mut routes = vec![];
 
-    // Header routes come first so headers will be added to any response down the chain.
+    // Access control comes first, so that denied clients receive nothing else.
     tracing::info!(
-        { CONFIG_RESPONSE_HEADERS_ENABLED } = config.headers.is_some(),
+        { CONFIG_ACCESS_ALLOW_ENABLED } = config
+            .access
+            .as_ref()
+            .is_some_and(|v| v.allow.is_some() || v.allow_env.is_some()),
+        { CONFIG_ACCESS_DENY_ENABLED } = config
+            .access
+            .as_ref()
+            .is_some_and(|v| v.deny.is_some() || v.deny_env.is_some()),
+        "config"
+    );
+    if let Some(ref access) = config.access {
+        routes.extend(generate_access_routes(access)?);
+    }
+
+    // Canonical host comes next, redirecting straight to HTTPS, to avoid chaining redirects.
+    tracing::info!(
+        { CONFIG_CANONICAL_HOST_ENABLED } = config.canonical_host.is_some(),
+        "config"
+    );
+    if let Some(ref canonical_host) = config.canonical_host {
+        routes.extend(generate_canonical_host_routes(canonical_host)?);
+    }
+
+    // HTTPS enforcement comes next, so that every other route only responds over HTTPS.
+    tracing::info!(
+        { CONFIG_FORCE_HTTPS_ENABLED } = config.force_https.is_some(),
+        { CONFIG_FORCE_HTTPS_HSTS_ENABLED } = config
+            .force_https
+            .as_ref()
+            .is_some_and(|v| v.hsts.is_some()),
         "config"
     );
-    if let Some(ref headers) = config.headers {
-        routes.extend(generate_response_headers_routes(headers));
+    if let Some(ref force_https) = config.force_https {
+        routes.extend(generate_force_https_routes(force_https)?);
     }
 
     let doc_root = config
@@ -37,55 +106,155 @@ pub(crate) fn caddy_json_config(
         .clone()
         .unwrap_or(String::from(DEFAULT_DOC_INDEX));
 
-    let mut static_file_handlers = vec![];
+    let html_file_policies = generate_html_file_policies(
+        config.content_security_policy.as_ref(),
+        &doc_root_files.inline_hashes,
+    );
+    tracing::info!(
+        { CONFIG_CONTENT_SECURITY_POLICY_ENABLED } = config.content_security_policy.is_some(),
+        { CONFIG_CONTENT_SECURITY_POLICY_HTML_FILES_COUNT } = html_file_policies.len(),
+        { CONFIG_CONTENT_SECURITY_POLICY_NONCE_ENABLED } = config
+            .content_security_policy
+            .as_ref()
+            .is_some_and(|v| v.nonce.is_some_and(|vv| vv)),
+        "config"
+    );
+
+    // Maintenance & auth are shared by the default site & every other site.
+    tracing::info!(
+        { CONFIG_MAINTENANCE_ENABLED } = config.maintenance.is_some(),
+        "config"
+    );
+    let maintenance_route = config
+        .maintenance
+        .as_ref()
+        .map(|maintenance| {
+            generate_maintenance_route(maintenance, &doc_root, &doc_index, &html_file_policies)
+        })
+        .transpose()?;
+
+    let mut auth_handlers = vec![];
 
-    let basic_auth_enabled = config
+    let basic_auth_config = config
         .caddy_server_opts
         .as_ref()
-        .is_some_and(|v| v.basic_auth.is_some_and(|vv| vv));
+        .and_then(|v| v.basic_auth.as_ref())
+        .filter(|v| v.is_enabled());
     tracing::info!(
-        { CONFIG_CADDY_SERVER_OPTS_BASIC_AUTH } = basic_auth_enabled,
+        { CONFIG_CADDY_SERVER_OPTS_BASIC_AUTH } = basic_auth_config.is_some(),
         "config"
     );
-    if basic_auth_enabled {
-        static_file_handlers.push(json!(
-        {
-            "handler": "subroute",
-            "routes": [{
-                "match": [{
-                    "expression": {
-                        "expr": "{env.WEB_BASIC_AUTH_DISABLED} != 'true'",
-                        "name": "basic_auth_required"
-                    }
-                }],
-                "handle": [{
-                    "handler": "authentication",
-                    "providers": {
-                        "http_basic": {
-                            "accounts": [{
-                                "username": "{env.WEB_BASIC_AUTH_USERNAME}",
-                                "password": "{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"
-                            }],
-                            "realm": "Restricted"
-                        }
-                    }
-                }]
-            }],
-        }));
+    if let Some(basic_auth_config) = basic_auth_config {
+        auth_handlers.push(generate_basic_auth_handler(
+            basic_auth_config,
+            basic_auth_accounts,
+        ));
     }
 
-    static_file_handlers.push(json!(
-    {
-        "handler": "encode",
-        "encodings": {
-            "zstd": { "level": "default" },
-            "gzip": { "level": 6 }
-        },
-        "prefer": ["zstd", "gzip"]
-    }));
+    tracing::info!(
+        { CONFIG_FORWARD_AUTH_ENABLED } = config.forward_auth.is_some(),
+        "config"
+    );
+    if let Some(ref forward_auth) = config.forward_auth {
+        auth_handlers.push(generate_forward_auth_handler(forward_auth)?);
+    }
+
+    // Sites come after the server-wide routes, each serving its hosts from its own document
+    // root, behind the same maintenance & auth. The rest of this config is the default site,
+    // for any other host.
+    let security_headers = config.security_headers.unwrap_or_default();
+    let sites = config.sites.as_deref().unwrap_or_default();
+    tracing::info!({ CONFIG_SITES_COUNT } = sites.len(), "config");
+    let mut site_error_routes = vec![];
+    for site in sites {
+        let (site_route, site_error_route) = generate_site_routes(
+            site,
+            security_headers,
+            maintenance_route.as_ref(),
+            &auth_handlers,
+        )?;
+        routes.push(site_route);
+        site_error_routes.push(site_error_route);
+    }
+
+    // Caching routes come before header routes, so that configured headers take precedence.
+    let caching_enabled = config.caching == Some(CachingMode::Auto);
+    tracing::info!(
+        { CONFIG_CACHING_ENABLED } = caching_enabled,
+        { CONFIG_CACHING_FINGERPRINTED_COUNT } = doc_root_files.fingerprinted_paths.len(),
+        "config"
+    );
+    if caching_enabled {
+        routes.extend(generate_caching_routes(&doc_root_files.fingerprinted_paths));
+    }
+
+    // Header routes come next so headers will be added to any response down the chain.
+    tracing::info!(
+        { CONFIG_RESPONSE_HEADERS_ENABLED } = config.headers.is_some(),
+        "config"
+    );
+    tracing::info!(
+        { CONFIG_SECURITY_HEADERS } = format!("{security_headers:?}").to_lowercase(),
+        "config"
+    );
+    routes.extend(generate_response_headers_routes(
+        config.headers.as_deref().unwrap_or_default(),
+        security_headers,
+    ));
+
+    // CORS comes after header routes, so that its headers take precedence, and answers
+    // preflight requests before any content is served.
+    tracing::info!({ CONFIG_CORS_ENABLED } = config.cors.is_some(), "config");
+    if let Some(ref cors) = config.cors {
+        routes.extend(generate_cors_routes(cors)?);
+    }
+
+    // Maintenance comes after header routes, so that they're added to its page, and responds
+    // before anything else is served, when toggled on at runtime.
+    if let Some(maintenance_route) = maintenance_route {
+        routes.push(maintenance_route);
+    }
+
+    // Redirects come before any content is served, and are terminal.
+    tracing::info!(
+        { CONFIG_REDIRECTS_ENABLED } = config.redirects.is_some(),
+        "config"
+    );
+    if let Some(ref redirects) = config.redirects {
+        routes.extend(generate_redirect_routes(redirects)?);
+    }
+
+    let clean_urls_enabled = config
+        .caddy_server_opts
+        .as_ref()
+        .is_some_and(|v| v.clean_urls.is_some_and(|vv| vv));
+
+    // Trailing slash redirects come before any content is served, and are terminal.
+    let trailing_slash = config.trailing_slash.unwrap_or_default();
+    tracing::info!(
+        { CONFIG_TRAILING_SLASH } = format!("{trailing_slash:?}").to_lowercase(),
+        "config"
+    );
+    routes.extend(generate_trailing_slash_routes(
+        trailing_slash,
+        &doc_root,
+        clean_urls_enabled,
+    ));
+
+    let mut static_file_handlers = auth_handlers;
+
+    static_file_handlers.push(generate_encode_handler());
 
     generate_static_response_handlers(config, &mut static_file_handlers)?;
 
+    static_file_handlers.extend(generate_error_status_handler(config.errors.as_ref()));
+
+    generate_proxy_handlers(config, &mut static_file_handlers)?;
+
+    generate_mount_handlers(config, &mut static_file_handlers)?;
+
+    generate_rewrite_handlers(config, &doc_root, &mut static_file_handlers)?;
+
     let templates_enabled = config
         .caddy_server_opts
         .as_ref()
@@ -101,10 +270,13 @@ pub(crate) fn caddy_json_config(
         }));
     }
 
-    let clean_urls_enabled = config
-        .caddy_server_opts
-        .as_ref()
-        .is_some_and(|v| v.clean_urls.is_some_and(|vv| vv));
+    generate_trailing_slash_handlers(
+        trailing_slash,
+        &doc_root,
+        clean_urls_enabled,
+        &mut static_file_handlers,
+    );
+
     tracing::info!(
         { CONFIG_CADDY_SERVER_OPTS_CLEAN_URLS } = clean_urls_enabled,
         "config"
@@ -132,13 +304,62 @@ pub(crate) fn caddy_json_config(
         }));
     }
 
-    static_file_handlers.push(json!(
+    let mut file_server_handler = json!(
     {
         "handler": "file_server",
         "root": doc_root,
         "index_names": vec![&doc_index],
         "pass_thru": true,
-    }));
+    });
+    // Without trailing slashes, directories are served by rewrite, instead of redirecting to add one.
+    if trailing_slash == TrailingSlashMode::Never {
+        file_server_handler["canonical_uris"] = json!(false);
+    }
+    // Serve build-time precompressed siblings; the encode handler remains as the fallback.
+    let precompress_enabled = config.precompress.is_some_and(|v| v);
+    tracing::info!(
+        { CONFIG_PRECOMPRESS_ENABLED } = precompress_enabled,
+        "config"
+    );
+    if precompress_enabled {
+        file_server_handler["precompressed"] = json!({"br": {}, "zstd": {}, "gzip": {}});
+        file_server_handler["precompressed_order"] = json!(["br", "zstd", "gzip"]);
+    }
+    // Serve ETags derived from file contents, written at build time; hide those ETag files.
+    let content_etags_enabled = config.content_etags.unwrap_or(false);
+    tracing::info!(
+        { CONFIG_CONTENT_ETAGS_ENABLED } = content_etags_enabled,
+        "config"
+    );
+    if content_etags_enabled {
+        file_server_handler["etag_file_extensions"] = json!([ETAG_FILE_EXTENSION]);
+        file_server_handler["hide"] = json!([format!("*{ETAG_FILE_EXTENSION}")]);
+    }
+    // SPA fallback comes after the other rewrites, so that it only serves the fallback document
+    // for paths that would otherwise be not found.
+    let spa_fallback_route = config
+        .spa_fallback
+        .as_ref()
+        .and_then(|v| generate_spa_fallback_route(v, &doc_root, &doc_index));
+    tracing::info!(
+        { CONFIG_SPA_FALLBACK_ENABLED } = spa_fallback_route.is_some(),
+        "config"
+    );
+    if let Some(spa_fallback_route) = spa_fallback_route {
+        static_file_handlers.push(json!(
+        {
+            "handler": "subroute",
+            "routes": [spa_fallback_route]
+        }));
+    }
+
+    generate_content_security_policy_handlers(
+        &html_file_policies,
+        &doc_index,
+        &mut static_file_handlers,
+    );
+
+    static_file_handlers.push(file_server_handler);
 
     routes.push(json!({
         "handle": static_file_handlers
@@ -148,8 +369,19 @@ pub(crate) fn caddy_json_config(
         &doc_root,
         &doc_index,
         config.errors.as_ref(),
+        &html_file_policies,
     ));
 
+    let error_routes = site_error_routes
+        .into_iter()
+        .chain(generate_error_routes(
+            &doc_root,
+            &doc_index,
+            config.errors.as_ref(),
+            &html_file_policies,
+        ))
+        .collect::<Vec<_>>();
+
     let mut server_logs_config = json!(null);
     let caddy_access_logs_config = config
         .caddy_server_opts
@@ -167,15 +399,33 @@ pub(crate) fn caddy_json_config(
         });
     }
 
+    let mut public_server = json!({
+        "listen": [":{env.PORT}"],
+        "logs": server_logs_config,
+        "routes": routes,
+        "errors": {
+            "routes": error_routes
+        }
+    });
+    let trusted_proxies_config = config
+        .caddy_server_opts
+        .as_ref()
+        .and_then(|v| v.trusted_proxies.as_ref());
+    let trusted_proxies_enabled =
+        trusted_proxies_config.is_none_or(|v| v.enabled.is_none_or(|vv| vv));
+    tracing::info!(
+        { CONFIG_CADDY_SERVER_OPTS_TRUSTED_PROXIES } = trusted_proxies_enabled,
+        "config"
+    );
+    if trusted_proxies_enabled {
+        generate_trusted_proxies(trusted_proxies_config, &mut public_server)?;
+    }
+
     Ok(json!({
         "apps": {
             "http": {
                 "servers": {
-                    "public": {
-                        "listen": [":{env.PORT}"],
-                        "logs": server_logs_config,
-                        "routes": routes
-                    }
+                    "public": public_server
                 }
             }
         },
@@ -215,6 +465,88 @@ pub(crate) fn caddy_json_config(
     }))
 }
 
+fn generate_encode_handler() -> serde_json::Value {
+    json!(
+    {
+        "handler": "encode",
+        "encodings": {
+            "zstd": { "level": "default" },
+            "gzip": { "level": 6 }
+        },
+        "prefer": ["zstd", "gzip"]
+    })
+}
+
+// A site's route, and its route for Caddy's error handling, both matching its hosts.
+// Its files are served as-is, so build-time processing of the top-level document root,
+// such as content ETags & Content-Security-Policy hashes, does not apply. Maintenance & auth
+// are the default site's, so that no site is served without them.
+fn generate_site_routes(
+    site: &SiteConfig,
+    security_headers: SecurityHeadersPreset,
+    maintenance_route: Option<&serde_json::Value>,
+    auth_handlers: &[serde_json::Value],
+) -> Result<(serde_json::Value, serde_json::Value), StaticWebServerBuildpackError> {
+    if site.hosts.is_empty() || site.hosts.iter().any(|host| host.trim().is_empty()) {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            "sites.hosts must be a list of one or more hostnames, like [\"www.example.com\"]"
+                .to_string(),
+        ));
+    }
+    let doc_root = site
+        .root
+        .as_ref()
+        .map_or(String::from(DEFAULT_DOC_ROOT), |path_buf| {
+            String::from(path_buf.to_string_lossy())
+        });
+    let doc_index = site
+        .index
+        .clone()
+        .unwrap_or(String::from(DEFAULT_DOC_INDEX));
+    let html_file_policies = HtmlFilePolicies::new();
+
+    let mut routes = generate_response_headers_routes(
+        site.headers.as_deref().unwrap_or_default(),
+        security_headers,
+    );
+    routes.extend(maintenance_route.cloned());
+    let mut handlers = auth_handlers.to_vec();
+    handlers.push(generate_encode_handler());
+    handlers.extend(generate_error_status_handler(site.errors.as_ref()));
+    handlers.push(json!({
+        "handler": "file_server",
+        "root": doc_root,
+        "index_names": vec![&doc_index],
+        "pass_thru": true,
+    }));
+    routes.push(json!({ "handle": handlers }));
+    routes.extend(generate_error_404_route(
+        &doc_root,
+        &doc_index,
+        site.errors.as_ref(),
+        &html_file_policies,
+    ));
+
+    let error_routes = generate_error_routes(
+        &doc_root,
+        &doc_index,
+        site.errors.as_ref(),
+        &html_file_policies,
+    );
+
+    let route = |routes: Vec<serde_json::Value>| {
+        json!({
+            "match": [{ "host": site.hosts }],
+            "handle": [{
+                "handler": "subroute",
+                "routes": routes
+            }],
+            "terminal": true
+        })
+    };
+    Ok((route(routes), route(error_routes)))
+}
+
 fn generate_static_response_handlers(
     config: &HerokuWebServerConfig,
     static_file_handlers: &mut Vec<serde_json::Value>,
@@ -281,437 +613,3891 @@ fn generate_static_response_handlers(
     Ok(())
 }
 
-fn generate_response_headers_routes(headers: &Vec<PathMatchedHeader>) -> Vec<serde_json::Value> {
-    // Group headers with the same matcher while preserving the order of the matchers
-    // by "when-first-seen".
-    let mut groups = IndexMap::<String, Vec<&PathMatchedHeader>>::new();
-    for header in headers {
-        if let Some(headers) = groups.get_mut(&header.path_matcher) {
-            headers.push(header);
-        } else {
-            groups.insert(header.path_matcher.clone(), vec![header]);
+// Ranges from env vars are written as `{env.NAME}` elements, which Caddy does not expand in
+// IP matchers, so they're replaced with the listed ranges at launch, by caddy-config-env.
+// An empty allowlist denies every client.
+fn generate_access_routes(
+    access: &AccessConfig,
+) -> Result<Vec<serde_json::Value>, StaticWebServerBuildpackError> {
+    let deny_ranges =
+        generate_access_ranges(access.deny.as_ref(), access.deny_env.as_ref(), "deny")?;
+    let allow_ranges =
+        generate_access_ranges(access.allow.as_ref(), access.allow_env.as_ref(), "allow")?;
+
+    let forbidden = json!([{
+        "handler": "error",
+        "status_code": 403
+    }]);
+    let mut routes = vec![];
+    if let Some(deny_ranges) = deny_ranges {
+        routes.push(json!({
+            "match": [{ "client_ip": { "ranges": deny_ranges } }],
+            "handle": forbidden,
+            "terminal": true
+        }));
+    }
+    if let Some(allow_ranges) = allow_ranges {
+        routes.push(json!({
+            "match": [{ "not": [{ "client_ip": { "ranges": allow_ranges } }] }],
+            "handle": forbidden,
+            "terminal": true
+        }));
+    }
+    Ok(routes)
+}
+
+fn generate_access_ranges(
+    ranges: Option<&Vec<String>>,
+    ranges_env: Option<&String>,
+    name: &str,
+) -> Result<Option<Vec<String>>, StaticWebServerBuildpackError> {
+    if ranges.is_none() && ranges_env.is_none() {
+        return Ok(None);
+    }
+    let mut result = vec![];
+    for range in ranges.into_iter().flatten() {
+        if !is_ip_range(range) {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                format!("access.{name} must be IP addresses or CIDR ranges, but got {range}"),
+            ));
+        }
+        result.push(range.clone());
+    }
+    if let Some(ranges_env) = ranges_env {
+        if !is_env_name(ranges_env) {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                format!(
+                    "access.{name}_env must be an environment variable name, but got {ranges_env}"
+                ),
+            ));
         }
+        result.push(format!("{{env.{ranges_env}}}"));
     }
+    Ok(Some(result))
+}
 
-    groups
-        .into_iter()
-        .map(|(matcher, headers)| {
-            let headers_as_hash_map = headers
-                .into_iter()
-                .map(|header| (header.key.clone(), vec![header.value.clone()]))
-                .collect::<HashMap<_, _>>();
+// The client IP, logged and matched, is the one forwarded by the Heroku router, which
+// connects from a private network address. Strict mode reads the client IP headers from
+// the right, so that an address prepended by the client is not trusted.
+// Only the Caddyfile expands the `private_ranges` shorthand, so it's expanded here for the JSON config.
+const PRIVATE_RANGES: &[&str] = &[
+    "192.168.0.0/16",
+    "172.16.0.0/12",
+    "10.0.0.0/8",
+    "127.0.0.1/8",
+    "fd00::/8",
+    "::1",
+];
 
-            json!({
-                "match": [{
-                    "path": vec![matcher]
-                }],
-                "handle": [{
-                    "handler": "headers",
-                    "response": {
-                        "set": headers_as_hash_map
-                    }
-                }]
-            })
-        })
-        .collect()
-}
+fn generate_trusted_proxies(
+    trusted_proxies: Option<&CaddyTrustedProxiesConfig>,
+    server: &mut serde_json::Value,
+) -> Result<(), StaticWebServerBuildpackError> {
+    let configured_ranges = trusted_proxies
+        .and_then(|v| v.ranges.clone())
+        .unwrap_or(vec!["private_ranges".to_string()]);
+    let mut ranges = vec![];
+    for range in configured_ranges {
+        if range == "private_ranges" {
+            ranges.extend(PRIVATE_RANGES.iter().map(ToString::to_string));
+        } else if is_ip_range(&range) {
+            ranges.push(range);
+        } else {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+                "caddy_server_opts.trusted_proxies.ranges must be \"private_ranges\", IP addresses, or CIDR ranges, but got {range}"
+            )));
+        }
+    }
+    let client_ip_headers = trusted_proxies
+        .and_then(|v| v.client_ip_headers.clone())
+        .unwrap_or(vec!["X-Forwarded-For".to_string()]);
 
-fn generate_error_404_route(
-    doc_root: &str,
-    doc_index: &str,
-    errors: Option<&ErrorsConfig>,
-) -> Vec<serde_json::Value> {
-    let error_config = errors.and_then(|errors| errors.custom_404_page.as_ref());
+    server["trusted_proxies"] = json!({
+        "source": "static",
+        "ranges": ranges
+    });
+    server["client_ip_headers"] = json!(client_ip_headers);
+    server["trusted_proxies_strict"] = json!(1);
+    Ok(())
+}
 
-    let custom_handlers = error_config.map(|error_config| {
-        let status_code = error_config.status.unwrap_or(404).to_string();
-        tracing::info!(
-            { CONFIG_ERROR_404_FILE_PATH } = error_config.file_path.to_string_lossy().to_string(),
-            "config"
-        );
-        tracing::info!({ CONFIG_ERROR_404_STATUS_CODE } = status_code, "config");
+// Redirects requests for any other hostname, such as the `*.herokuapp.com` or `www.` variant.
+// When read from an env var at launch, an unset or empty var disables the redirect.
+fn generate_canonical_host_routes(
+    canonical_host: &CanonicalHostConfig,
+) -> Result<Vec<serde_json::Value>, StaticWebServerBuildpackError> {
+    let status_code = canonical_host.status.unwrap_or(301);
+    if ![301, 308].contains(&status_code) {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!("canonical_host.status must be one of 301 or 308, but got {status_code}"),
+        ));
+    }
 
-        json!([
-            {
-                "handler": "rewrite",
-                "uri": error_config.file_path,
-            },
-            {
-                "handler": "headers",
-                "response": {
-                    "set": {
-                        "Cache-Control": ["no-store, no-cache, must-revalidate"],
-                        "Pragma": ["no-cache"]
-                    }
-                }
-            },
+    let (expr, location_host) = match (&canonical_host.host, &canonical_host.host_env) {
+        (Some(host), None) => {
+            if host.is_empty()
+                || !host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
             {
-                "handler": "file_server",
-                "root": doc_root,
-                "status_code": status_code,
-                "index_names": vec![doc_index],
-                "pass_thru": false
+                return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+                    "canonical_host.host must be a hostname, without scheme, port, or path, but got {host}"
+                )));
+            }
+            (format!("{{http.request.host}} != '{host}'"), host.clone())
+        }
+        (None, Some(host_env)) => {
+            if !is_env_name(host_env) {
+                return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+                    "canonical_host.host_env must be an environment variable name, but got {host_env}"
+                )));
             }
-        ])
+            (
+                format!("{{env.{host_env}}} != '' && {{http.request.host}} != {{env.{host_env}}}"),
+                format!("{{env.{host_env}}}"),
+            )
+        }
+        _ => {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                "exactly one of host or host_env must be set for canonical_host".to_string(),
+            ));
+        }
+    };
+
+    let mut host_match = json!({
+        "expression": {
+            "expr": expr,
+            "name": "canonical_host"
+        }
     });
+    if let Some(ref path_exclusions) = canonical_host.path_exclusions {
+        host_match["not"] = json!([{ "path": path_exclusions }]);
+    }
 
-    let default_handlers = json!([{
-        "handler": "static_response",
-        "status_code": "404",
-        "headers": {
-            "Content-Type": ["text/html"],
-            "Cache-Control": ["no-store, no-cache, must-revalidate"],
-            "Pragma": ["no-cache"]
-        },
-        "body": DEFAULT_404_HTML
-    }]);
+    Ok(vec![json!({
+        "match": [host_match],
+        "handle": [{
+            "handler": "static_response",
+            "status_code": status_code,
+            "headers": {
+                "Location": [format!("https://{location_host}{{http.request.uri}}")]
+            }
+        }],
+        "terminal": true
+    })])
+}
 
-    let path_exclusions = error_config.and_then(|ec| ec.path_exclusions.as_ref());
+// The request `Origin` is checked against the allowed origins, so that a matching origin is
+// echoed back; only a wildcard without credentials may respond with `*` instead.
+fn generate_cors_routes(
+    cors: &[CorsConfig],
+) -> Result<Vec<serde_json::Value>, StaticWebServerBuildpackError> {
+    let mut routes = vec![];
+    for cors_config in cors {
+        validate_cors_config(cors_config)?;
+
+        let allow_credentials = cors_config.allow_credentials.is_some_and(|v| v);
+        let echo_origin = !is_any_cors_origin(cors_config);
+
+        let mut cors_headers = serde_json::Map::new();
+        cors_headers.insert(
+            "Access-Control-Allow-Origin".to_string(),
+            json!([if echo_origin {
+                "{http.request.header.Origin}"
+            } else {
+                "*"
+            }]),
+        );
+        if allow_credentials {
+            cors_headers.insert(
+                "Access-Control-Allow-Credentials".to_string(),
+                json!(["true"]),
+            );
+        }
+
+        let mut preflight_headers = cors_headers.clone();
+        preflight_headers.insert(
+            "Access-Control-Allow-Methods".to_string(),
+            json!([cors_config
+                .allowed_methods
+                .clone()
+                .unwrap_or(vec!["GET".to_string(), "HEAD".to_string()])
+                .join(", ")]),
+        );
+        if let Some(ref allowed_headers) = cors_config.allowed_headers {
+            preflight_headers.insert(
+                "Access-Control-Allow-Headers".to_string(),
+                json!([allowed_headers.join(", ")]),
+            );
+        }
+        if let Some(max_age) = cors_config.max_age {
+            preflight_headers.insert(
+                "Access-Control-Max-Age".to_string(),
+                json!([max_age.to_string()]),
+            );
+        }
+
+        let mut cors_routes = vec![];
+        // Echoed responses vary by origin, whether or not it's allowed.
+        if echo_origin {
+            cors_routes.push(json!({
+                "handle": [{
+                    "handler": "headers",
+                    "response": {
+                        "add": { "Vary": ["Origin"] }
+                    }
+                }]
+            }));
+        }
+        let origin_match = generate_cors_origin_match(cors_config);
+        let mut preflight_match = origin_match.clone();
+        preflight_match["method"] = json!(["OPTIONS"]);
+        preflight_match["header"] = json!({ "Access-Control-Request-Method": [] });
+        cors_routes.push(json!({
+            "match": [preflight_match],
+            "handle": [{
+                "handler": "static_response",
+                "status_code": 204,
+                "headers": preflight_headers
+            }],
+            "terminal": true
+        }));
+        cors_routes.push(json!({
+            "match": [origin_match],
+            "handle": [{
+                "handler": "headers",
+                "response": {
+                    "set": cors_headers
+                }
+            }]
+        }));
+
+        routes.push(json!({
+            "match": [{ "path": [cors_config.path_matcher] }],
+            "handle": [{
+                "handler": "subroute",
+                "routes": cors_routes
+            }]
+        }));
+    }
+    Ok(routes)
+}
+
+// Values are written into CEL expressions & response headers, so are constrained to what's valid.
+fn validate_cors_config(cors_config: &CorsConfig) -> Result<(), StaticWebServerBuildpackError> {
+    let allowed_origins = cors_config.allowed_origins.as_deref().unwrap_or_default();
+    if allowed_origins.is_empty() && cors_config.allowed_origins_env.is_none() {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!(
+                "allowed_origins or allowed_origins_env must be set for cors path_matcher {}",
+                cors_config.path_matcher
+            ),
+        ));
+    }
+    if let Some(origin) = allowed_origins
+        .iter()
+        .find(|o| *o != "*" && !CORS_ORIGIN_REGEX.is_match(o))
+    {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+            "cors allowed_origins must be \"*\" or a scheme & host, like https://example.com, but got {origin}"
+        )));
+    }
+    // Credentialed requests from any origin would let every website read responses as the user.
+    if is_any_cors_origin(cors_config) && cors_config.allow_credentials.is_some_and(|v| v) {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+            "cors allowed_origins \"*\" cannot be combined with allow_credentials for path_matcher {}, list the allowed origins instead",
+            cors_config.path_matcher
+        )));
+    }
+    if let Some(name) = cors_config
+        .allowed_origins_env
+        .as_ref()
+        .filter(|v| !is_env_name(v))
+    {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!(
+                "cors allowed_origins_env must be an environment variable name, but got {name}"
+            ),
+        ));
+    }
+    if let Some(token) = cors_config
+        .allowed_methods
+        .iter()
+        .chain(cors_config.allowed_headers.iter())
+        .flatten()
+        .find(|v| !HTTP_TOKEN_REGEX.is_match(v))
+    {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+            "cors allowed_methods & allowed_headers must be HTTP methods or header names, but got {token}"
+        )));
+    }
+    Ok(())
+}
+
+fn is_any_cors_origin(cors_config: &CorsConfig) -> bool {
+    cors_config
+        .allowed_origins
+        .as_ref()
+        .is_some_and(|v| v.iter().any(|o| o == "*"))
+}
+
+fn generate_cors_origin_match(cors_config: &CorsConfig) -> serde_json::Value {
+    let allowed_origins = cors_config.allowed_origins.as_deref().unwrap_or_default();
+    let mut origin_exprs = vec![];
+    if allowed_origins.iter().any(|o| o == "*") {
+        origin_exprs.push("true".to_string());
+    } else if !allowed_origins.is_empty() {
+        origin_exprs.push(format!(
+            "{{http.request.header.Origin}} in [{}]",
+            allowed_origins
+                .iter()
+                .map(|o| format!("'{o}'"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+    // A comma-separated list, so each origin is matched between commas.
+    if let Some(ref env_name) = cors_config.allowed_origins_env {
+        origin_exprs.push(format!(
+            "(',' + {{env.{env_name}}} + ',').contains(',' + {{http.request.header.Origin}} + ',')"
+        ));
+    }
+    json!({
+        "expression": {
+            "expr": format!(
+                "{{http.request.header.Origin}} != '' && ({})",
+                origin_exprs.join(" || ")
+            ),
+            "name": "cors_origin_allowed"
+        }
+    })
+}
+
+// The Heroku router terminates TLS, so the original request scheme is only known
+// from the X-Forwarded-Proto header. Requests without it, like local ones, are not redirected.
+fn generate_force_https_routes(
+    force_https: &ForceHttpsConfig,
+) -> Result<Vec<serde_json::Value>, StaticWebServerBuildpackError> {
+    let status_code = force_https.status.unwrap_or(301);
+    if ![301, 308].contains(&status_code) {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!("force_https.status must be one of 301 or 308, but got {status_code}"),
+        ));
+    }
+
+    let mut http_match = json!({
+        "header": {
+            "X-Forwarded-Proto": ["http"]
+        }
+    });
+    if let Some(ref path_exclusions) = force_https.path_exclusions {
+        http_match["not"] = json!([{ "path": path_exclusions }]);
+    }
+
+    let mut routes = vec![json!({
+        "match": [http_match],
+        "handle": [{
+            "handler": "static_response",
+            "status_code": status_code,
+            "headers": {
+                "Location": ["https://{http.request.host}{http.request.uri}"]
+            }
+        }],
+        "terminal": true
+    })];
+
+    // Browsers ignore Strict-Transport-Security received over plain HTTP.
+    if let Some(ref hsts) = force_https.hsts {
+        let mut hsts_value = format!("max-age={}", hsts.max_age.unwrap_or(31_536_000));
+        if hsts.include_subdomains.is_some_and(|v| v) {
+            hsts_value.push_str("; includeSubDomains");
+        }
+        if hsts.preload.is_some_and(|v| v) {
+            hsts_value.push_str("; preload");
+        }
+        routes.push(json!({
+            "match": [{
+                "header": {
+                    "X-Forwarded-Proto": ["https"]
+                }
+            }],
+            "handle": [{
+                "handler": "headers",
+                "response": {
+                    "set": {
+                        "Strict-Transport-Security": [hsts_value]
+                    }
+                }
+            }]
+        }));
+    }
+
+    Ok(routes)
+}
+
+fn generate_redirect_routes(
+    redirects: &[RedirectConfig],
+) -> Result<Vec<serde_json::Value>, StaticWebServerBuildpackError> {
+    let mut redirect_routes = vec![];
+    for (index, redirect) in redirects.iter().enumerate() {
+        let status_code = redirect.status.unwrap_or(301);
+        if ![301, 302, 307, 308].contains(&status_code) {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                format!(
+                    "redirects.status must be one of 301, 302, 307, or 308, but got {status_code}"
+                ),
+            ));
+        }
+
+        let matcher_name = format!("redirect_{index}");
+        let (path_match, captures_count) = generate_captured_path_match(
+            redirect.from.as_ref(),
+            redirect.from_regex.as_ref(),
+            &matcher_name,
+            "redirects",
+        )?;
+        let location =
+            substitute_captures(&redirect.to, &matcher_name, captures_count, "redirects")?;
+
+        let handle = generate_redirect_handlers(
+            &location,
+            status_code,
+            redirect.preserve_query.is_some_and(|v| v),
+        );
+
+        redirect_routes.push(json!({
+            "match": [path_match],
+            "handle": handle,
+            "terminal": true
+        }));
+    }
+    Ok(redirect_routes)
+}
+
+// Redirects paths to the policy's form, but only when the redirected path will be served from the
+// document root, so that proxied paths and client-side routes are left alone.
+fn generate_trailing_slash_routes(
+    trailing_slash: TrailingSlashMode,
+    doc_root: &str,
+    clean_urls_enabled: bool,
+) -> Vec<serde_json::Value> {
+    match trailing_slash {
+        TrailingSlashMode::Ignore => vec![],
+        TrailingSlashMode::Always => {
+            let mut try_files = vec!["{http.request.uri.path}/"];
+            if clean_urls_enabled {
+                try_files.push("{http.request.uri.path}.html");
+            }
+            vec![json!({
+                "match": [{
+                    "not": [{ "path": ["*/"] }, protocol_relative_path_matcher()],
+                    "file": {
+                        "root": doc_root,
+                        "try_files": try_files
+                    }
+                }],
+                "handle": generate_redirect_handlers("{http.request.uri.path}/", 301, true),
+                "terminal": true
+            })]
+        }
+        TrailingSlashMode::Never => {
+            let mut try_files = vec!["{http.regexp.trailing_slash.1}/"];
+            if clean_urls_enabled {
+                try_files.push("{http.regexp.trailing_slash.1}.html");
+            }
+            // Nested, so that the path capture is available to the file matcher.
+            vec![json!({
+                "match": [{
+                    "path_regexp": {
+                        "name": "trailing_slash",
+                        "pattern": "^(.+)/$"
+                    },
+                    "not": [protocol_relative_path_matcher()]
+                }],
+                "handle": [{
+                    "handler": "subroute",
+                    "routes": [{
+                        "match": [{
+                            "file": {
+                                "root": doc_root,
+                                "try_files": try_files
+                            }
+                        }],
+                        "handle": generate_redirect_handlers("{http.regexp.trailing_slash.1}", 301, true),
+                        "terminal": true
+                    }]
+                }]
+            })]
+        }
+    }
+}
+
+// Paths starting with `//`, which would redirect to another host as a protocol-relative
+// `Location`, like `//evil.example/` to `//evil.example`.
+fn protocol_relative_path_matcher() -> serde_json::Value {
+    json!({ "path_regexp": { "pattern": "^//" } })
+}
+
+// Serves paths in the policy's form, which the file server would not otherwise resolve:
+// * always: `/page/` from `page.html` (with clean URLs)
+// * never: `/dir` from `dir/index.html`
+fn generate_trailing_slash_handlers(
+    trailing_slash: TrailingSlashMode,
+    doc_root: &str,
+    clean_urls_enabled: bool,
+    handlers: &mut Vec<serde_json::Value>,
+) {
+    match trailing_slash {
+        TrailingSlashMode::Always if clean_urls_enabled => handlers.push(json!({
+            "handler": "subroute",
+            "routes": [{
+                "match": [{
+                    "path_regexp": {
+                        "name": "trailing_slash_html",
+                        "pattern": "^(.+)/$"
+                    }
+                }],
+                "handle": [{
+                    "handler": "subroute",
+                    "routes": [{
+                        "match": [{
+                            "file": {
+                                "root": doc_root,
+                                "try_files": ["{http.regexp.trailing_slash_html.1}.html"]
+                            }
+                        }],
+                        "handle": [{
+                            "handler": "rewrite",
+                            "uri": "{http.matchers.file.relative}"
+                        }]
+                    }]
+                }]
+            }]
+        })),
+        TrailingSlashMode::Never => handlers.push(json!({
+            "handler": "subroute",
+            "routes": [{
+                "match": [{
+                    "not": [{ "path": ["*/"] }],
+                    "file": {
+                        "root": doc_root,
+                        "try_files": ["{http.request.uri.path}/"]
+                    }
+                }],
+                "handle": [{
+                    "handler": "rewrite",
+                    "uri": "{http.matchers.file.relative}"
+                }]
+            }]
+        })),
+        _ => {}
+    }
+}
+
+/// Content-Security-Policy for an HTML file, with whether it's rendered with a per-request nonce.
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
+struct HtmlFilePolicy {
+    header_name: &'static str,
+    policy: String,
+    nonce: bool,
+}
+
+/// Content-Security-Policy for each HTML file, keyed by its URL path.
+type HtmlFilePolicies = BTreeMap<String, HtmlFilePolicy>;
+
+// In nonce mode, files with inline content are allowed by the nonce instead of their hashes,
+// those files having been written with nonce placeholders at build time.
+fn generate_html_file_policies(
+    content_security_policy: Option<&ContentSecurityPolicyConfig>,
+    inline_hashes: &BTreeMap<String, InlineHashes>,
+) -> HtmlFilePolicies {
+    let Some(content_security_policy) = content_security_policy else {
+        return HtmlFilePolicies::new();
+    };
+    let header_name = if content_security_policy.report_only.is_some_and(|v| v) {
+        "Content-Security-Policy-Report-Only"
+    } else {
+        "Content-Security-Policy"
+    };
+    let nonce_enabled = content_security_policy.nonce.is_some_and(|v| v);
+    let nonce_source = BTreeSet::from([format!("'nonce-{NONCE_PLACEHOLDER}'")]);
+    inline_hashes
+        .iter()
+        .map(|(url_path, hashes)| {
+            let nonce = nonce_enabled && (!hashes.scripts.is_empty() || !hashes.styles.is_empty());
+            let sources = if nonce {
+                &InlineHashes {
+                    scripts: nonce_source.clone(),
+                    styles: nonce_source.clone(),
+                }
+            } else {
+                hashes
+            };
+            (
+                url_path.clone(),
+                HtmlFilePolicy {
+                    header_name,
+                    policy: merge_inline_sources(&content_security_policy.policy, sources),
+                    nonce,
+                },
+            )
+        })
+        .collect()
+}
+
+// Adds hash or nonce sources to the `script-src` & `style-src` directives. When a directive is
+// not in the policy, it falls back to `default-src`, so it's added with those sources, to keep
+// the same restrictions. Without either directive, inline content is already allowed.
+fn merge_inline_sources(policy: &str, hashes: &InlineHashes) -> String {
+    let mut directives: Vec<(String, Vec<String>)> = policy
+        .split(';')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| {
+            let mut tokens = directive.split_whitespace().map(String::from);
+            let name = tokens.next().unwrap_or_default().to_ascii_lowercase();
+            (name, tokens.collect())
+        })
+        .collect();
+
+    for (directive_name, directive_hashes) in [
+        ("script-src", &hashes.scripts),
+        ("style-src", &hashes.styles),
+    ] {
+        if directive_hashes.is_empty() {
+            continue;
+        }
+        let index = if let Some(index) = directives.iter().position(|(n, _)| n == directive_name) {
+            index
+        } else if let Some((_, default_sources)) =
+            directives.iter().find(|(n, _)| n == "default-src")
+        {
+            directives.push((directive_name.to_string(), default_sources.clone()));
+            directives.len() - 1
+        } else {
+            continue;
+        };
+        let sources = &mut directives[index].1;
+        sources.retain(|source| source != "'none'");
+        sources.extend(directive_hashes.iter().cloned());
+    }
+
+    directives
+        .into_iter()
+        .map(|(name, sources)| {
+            std::iter::once(name)
+                .chain(sources)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Sets each HTML file's policy, matching the request path after any rewrites, whether
+// the file itself or its directory, for index documents.
+fn generate_content_security_policy_handlers(
+    html_file_policies: &HtmlFilePolicies,
+    doc_index: &str,
+    handlers: &mut Vec<serde_json::Value>,
+) {
+    if html_file_policies.is_empty() {
+        return;
+    }
+    let index_suffix = format!("/{doc_index}");
+    let mut paths_by_policy = BTreeMap::<&HtmlFilePolicy, Vec<String>>::new();
+    for (url_path, html_file_policy) in html_file_policies {
+        let paths = paths_by_policy.entry(html_file_policy).or_default();
+        paths.push(url_path.clone());
+        if let Some(dir_path) = url_path.strip_suffix(&index_suffix) {
+            paths.push(format!("{dir_path}/"));
+        }
+    }
+    handlers.push(json!({
+        "handler": "subroute",
+        "routes": paths_by_policy
+            .into_iter()
+            .map(|(html_file_policy, paths)| {
+                let mut handle = vec![json!({
+                    "handler": "headers",
+                    "response": {
+                        "set": {
+                            html_file_policy.header_name: [html_file_policy.policy]
+                        }
+                    }
+                })];
+                if html_file_policy.nonce {
+                    handle.push(generate_nonce_templates_handler());
+                }
+                json!({
+                    "match": [{
+                        "path": paths
+                    }],
+                    "handle": handle
+                })
+            })
+            .collect::<Vec<_>>()
+    }));
+}
+
+// Renders the nonce placeholders written into HTML files, with the same nonce as the header.
+fn generate_nonce_templates_handler() -> serde_json::Value {
+    json!({
+        "handler": "templates",
+        "delimiters": NONCE_TEMPLATE_DELIMITERS
+    })
+}
+
+// Accounts are the single account from env vars, the accounts listed in WEB_BASIC_AUTH_USERS,
+// which are expanded at launch by caddy-config-env, and those read from the users file.
+// Accounts from unset env vars are removed at launch too.
+fn generate_basic_auth_handler(
+    basic_auth_config: &CaddyBasicAuthConfig,
+    basic_auth_accounts: &[BasicAuthAccount],
+) -> serde_json::Value {
+    let mut accounts = vec![
+        json!({
+            "username": "{env.WEB_BASIC_AUTH_USERNAME}",
+            "password": "{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"
+        }),
+        json!({
+            "username": format!("{{env.{BASIC_AUTH_USERS_ENV}}}")
+        }),
+    ];
+    accounts.extend(basic_auth_accounts.iter().map(|v| json!(v)));
+
+    let mut basic_auth_match = json!({
+        "expression": {
+            "expr": "{env.WEB_BASIC_AUTH_DISABLED} != 'true'",
+            "name": "basic_auth_required"
+        }
+    });
+    if let Some(opts) = basic_auth_config.opts() {
+        if let Some(ref paths) = opts.paths {
+            basic_auth_match["path"] = json!(paths);
+        }
+        if let Some(ref exclude_paths) = opts.exclude_paths {
+            basic_auth_match["not"] = json!([{ "path": exclude_paths }]);
+        }
+    }
+
+    json!({
+        "handler": "subroute",
+        "routes": [{
+            "match": [basic_auth_match],
+            "handle": [{
+                "handler": "authentication",
+                "providers": {
+                    "http_basic": {
+                        "accounts": accounts,
+                        "realm": "Restricted"
+                    }
+                }
+            }]
+        }],
+    })
+}
+
+// Responds with the maintenance page when WEB_MAINTENANCE_MODE=true, evaluated per request, so
+// that maintenance is toggled without redeploying. Bypassing clients are served as usual.
+// Without a page, the error routes respond, with any custom error page for the status.
+fn generate_maintenance_route(
+    maintenance: &MaintenanceConfig,
+    doc_root: &str,
+    doc_index: &str,
+    html_file_policies: &HtmlFilePolicies,
+) -> Result<serde_json::Value, StaticWebServerBuildpackError> {
+    let status_code = maintenance.status.unwrap_or(503);
+    if !(400..=599).contains(&status_code) {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!(
+            "maintenance.status must be an HTTP error status from 400 to 599, but got {status_code}"
+        ),
+        ));
+    }
+
+    let mut bypass_matchers = vec![];
+    if let Some(ref bypass_ips) = maintenance.bypass_ips {
+        if let Some(range) = bypass_ips.iter().find(|v| !is_ip_range(v)) {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                format!(
+                    "maintenance.bypass_ips must be IP addresses or CIDR ranges, but got {range}"
+                ),
+            ));
+        }
+        bypass_matchers.push(json!({ "client_ip": { "ranges": bypass_ips } }));
+    }
+    if let Some(ref bypass_cookie) = maintenance.bypass_cookie {
+        if !HTTP_TOKEN_REGEX.is_match(bypass_cookie) {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                format!("maintenance.bypass_cookie must be a cookie name, but got {bypass_cookie}"),
+            ));
+        }
+        bypass_matchers.push(json!({
+            "expression": {
+                "expr": format!("{{env.{MAINTENANCE_BYPASS_TOKEN_ENV}}} != '' && {{http.request.cookie.{bypass_cookie}}} == {{env.{MAINTENANCE_BYPASS_TOKEN_ENV}}}"),
+                "name": "maintenance_bypass_cookie"
+            }
+        }));
+    }
+    if let Some(ref path_exclusions) = maintenance.path_exclusions {
+        bypass_matchers.push(json!({ "path": path_exclusions }));
+    }
+
+    let mut matcher = json!({
+        "expression": {
+            "expr": "{env.WEB_MAINTENANCE_MODE} == 'true'",
+            "name": "maintenance_mode"
+        }
+    });
+    if !bypass_matchers.is_empty() {
+        matcher["not"] = json!(bypass_matchers);
+    }
+
+    let mut handlers = vec![];
+    if let Some(retry_after) = maintenance.retry_after {
+        handlers.push(json!({
+            "handler": "headers",
+            "response": {
+                "set": { "Retry-After": [retry_after.to_string()] }
+            }
+        }));
+    }
+    if let Some(ref file_path) = maintenance.file_path {
+        let page_handlers = generate_custom_error_handlers(
+            doc_root,
+            doc_index,
+            &ErrorConfig {
+                file_path: file_path.clone(),
+                ..ErrorConfig::default()
+            },
+            status_code,
+            html_file_policies,
+        );
+        handlers.extend(page_handlers.as_array().cloned().unwrap_or_default());
+    } else {
+        handlers.push(json!({
+            "handler": "error",
+            "status_code": status_code
+        }));
+    }
+
+    Ok(json!({
+        "match": [matcher],
+        "handle": handlers,
+        "terminal": true
+    }))
+}
+
+// Asks the auth service whether to serve each request, with a GET subrequest like Caddy's
+// `forward_auth` directive. A 2xx response continues to the following handlers, copying the
+// configured headers into the request, while any other response is sent to the client, such as
+// a 401 or a redirect to sign in. The URL from the env var is split into the upstream & the
+// subrequest URI at launch, by caddy-config-env, from the `forward_auth_url` field.
+fn generate_forward_auth_handler(
+    forward_auth: &ForwardAuthConfig,
+) -> Result<serde_json::Value, StaticWebServerBuildpackError> {
+    let url_env = match forward_auth.url_env {
+        Some(ref url_env) if is_env_name(url_env) => url_env,
+        ref url_env => {
+            return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                format!(
+                    "forward_auth.url_env must be an environment variable name, but got {}",
+                    url_env.as_deref().unwrap_or_default()
+                ),
+            ));
+        }
+    };
+
+    let copy_headers = forward_auth.copy_headers.clone().unwrap_or_default();
+    if let Some(header) = copy_headers.iter().find(|v| !HTTP_TOKEN_REGEX.is_match(v)) {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!("forward_auth.copy_headers must be header names, but got \"{header}\""),
+        ));
+    }
+
+    // Headers are removed first, so that a client cannot set them when the auth response doesn't.
+    let mut copy_header_routes = vec![];
+    if !copy_headers.is_empty() {
+        copy_header_routes.push(json!({
+            "handle": [{
+                "handler": "headers",
+                "request": { "delete": copy_headers }
+            }]
+        }));
+    }
+    for header in &copy_headers {
+        let placeholder = format!("{{http.reverse_proxy.header.{header}}}");
+        copy_header_routes.push(json!({
+            "match": [{ "not": [{ "vars": { placeholder.clone(): [""] } }] }],
+            "handle": [{
+                "handler": "headers",
+                "request": { "set": { header: [placeholder] } }
+            }]
+        }));
+    }
+
+    let mut route = json!({
+        "handle": [{
+            "handler": "reverse_proxy",
+            "forward_auth_url": format!("{{env.{url_env}}}"),
+            "headers": {
+                "request": {
+                    "set": {
+                        "X-Forwarded-Method": ["{http.request.method}"],
+                        "X-Forwarded-Uri": ["{http.request.uri}"]
+                    }
+                }
+            },
+            "handle_response": [{
+                "match": { "status_code": [2] },
+                "routes": copy_header_routes
+            }]
+        }]
+    });
+    if let Some(ref path_exclusions) = forward_auth.path_exclusions {
+        route["match"] = json!([{ "not": [{ "path": path_exclusions }] }]);
+    }
+
+    Ok(json!({
+        "handler": "subroute",
+        "routes": [route]
+    }))
+}
+
+// Responds with a redirect to the location, optionally appending the request's querystring.
+fn generate_redirect_handlers(
+    location: &str,
+    status_code: u16,
+    preserve_query: bool,
+) -> serde_json::Value {
+    let redirect_response = |location: String| {
+        json!({
+            "handler": "static_response",
+            "status_code": status_code,
+            "headers": { "Location": [location] }
+        })
+    };
+
+    if preserve_query {
+        let query_separator = if location.contains('?') { '&' } else { '?' };
+        json!([{
+            "handler": "subroute",
+            "routes": [
+                {
+                    "match": [{
+                        "expression": {
+                            "expr": "{http.request.uri.query} != ''",
+                            "name": "redirect_query_present"
+                        }
+                    }],
+                    "handle": [redirect_response(format!(
+                        "{location}{query_separator}{{http.request.uri.query}}"
+                    ))]
+                },
+                {
+                    "handle": [redirect_response(location.to_string())]
+                }
+            ]
+        }])
+    } else {
+        json!([redirect_response(location.to_string())])
+    }
+}
+
+// Returns the Caddy path matcher for a `from` wildcard path or `from_regex`, along with
+// how many captures it provides. Wildcard paths are translated to a regular expression,
+// capturing each `*`.
+fn generate_captured_path_match(
+    from: Option<&String>,
+    from_regex: Option<&String>,
+    matcher_name: &str,
+    config_name: &str,
+) -> Result<(serde_json::Value, usize), StaticWebServerBuildpackError> {
+    match (from, from_regex) {
+        (Some(from), None) if from.contains('*') => {
+            let pattern = format!(
+                "^{}$",
+                from.split('*')
+                    .map(regex::escape)
+                    .collect::<Vec<_>>()
+                    .join("(.*)")
+            );
+            Ok((
+                json!({"path_regexp": {"name": matcher_name, "pattern": pattern}}),
+                from.matches('*').count(),
+            ))
+        }
+        (Some(from), None) => Ok((json!({"path": [from]}), 0)),
+        (None, Some(from_regex)) => {
+            let compiled_regex = Regex::new(from_regex).map_err(|e| {
+                StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+                    "{config_name}.from_regex \"{from_regex}\" is not a valid regular expression: {e}"
+                ))
+            })?;
+            Ok((
+                json!({"path_regexp": {"name": matcher_name, "pattern": from_regex}}),
+                compiled_regex.captures_len() - 1,
+            ))
+        }
+        _ => Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!("either from or from_regex must be set for {config_name}"),
+        )),
+    }
+}
+
+// Replaces capture references `{1}`, `{2}`… in a target with the placeholders Caddy
+// exposes for the named regexp matcher.
+fn substitute_captures(
+    target: &str,
+    matcher_name: &str,
+    captures_count: usize,
+    config_name: &str,
+) -> Result<String, StaticWebServerBuildpackError> {
+    let mut invalid_capture = false;
+    let substituted = CAPTURE_REFERENCE_REGEX
+        .replace_all(target, |caps: &regex::Captures| {
+            let capture_index: usize = caps[1].parse().unwrap_or(usize::MAX);
+            invalid_capture |= capture_index == 0 || capture_index > captures_count;
+            format!("{{http.regexp.{matcher_name}.{capture_index}}}")
+        })
+        .to_string();
+    if invalid_capture {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!(
+                "{config_name}.to \"{target}\" refers to a capture that its from pattern does not have"
+            ),
+        ));
+    }
+    Ok(substituted)
+}
+
+fn generate_rewrite_handlers(
+    config: &HerokuWebServerConfig,
+    doc_root: &str,
+    static_file_handlers: &mut Vec<serde_json::Value>,
+) -> Result<(), StaticWebServerBuildpackError> {
+    tracing::info!(
+        { CONFIG_REWRITES_ENABLED } = config.rewrites.is_some(),
+        "config"
+    );
+    if let Some(ref rewrites) = config.rewrites {
+        let mut rewrite_routes = vec![];
+        for (index, rewrite) in rewrites.iter().enumerate() {
+            let matcher_name = format!("rewrite_{index}");
+            let (path_match, captures_count) = generate_captured_path_match(
+                rewrite.from.as_ref(),
+                rewrite.from_regex.as_ref(),
+                &matcher_name,
+                "rewrites",
+            )?;
+            let uri = substitute_captures(&rewrite.to, &matcher_name, captures_count, "rewrites")?;
+
+            let rewrite_handler = json!({
+                "handler": "rewrite",
+                "uri": uri
+            });
+
+            // Grouped routes are mutually exclusive, so only the first matching rewrite applies.
+            if rewrite.if_target_exists.is_some_and(|v| v) {
+                // Nested, so that the path capture is available to the file matcher. Only the
+                // nested route is grouped, so that a missing target falls through to the next rewrite.
+                rewrite_routes.push(json!({
+                    "match": [path_match],
+                    "handle": [{
+                        "handler": "subroute",
+                        "routes": [{
+                            "group": "rewrites",
+                            "match": [{
+                                "file": {
+                                    "root": doc_root,
+                                    "try_files": [uri]
+                                }
+                            }],
+                            "handle": [rewrite_handler]
+                        }]
+                    }]
+                }));
+            } else {
+                rewrite_routes.push(json!({
+                    "group": "rewrites",
+                    "match": [path_match],
+                    "handle": [rewrite_handler]
+                }));
+            }
+        }
+
+        static_file_handlers.push(json!(
+        {
+            "handler": "subroute",
+            "routes": rewrite_routes,
+        }));
+    }
+    Ok(())
+}
+
+fn generate_proxy_handlers(
+    config: &HerokuWebServerConfig,
+    static_file_handlers: &mut Vec<serde_json::Value>,
+) -> Result<(), StaticWebServerBuildpackError> {
+    tracing::info!(
+        { CONFIG_PROXIES_ENABLED } = config.proxies.is_some(),
+        "config"
+    );
+    if let Some(ref proxies) = config.proxies {
+        for proxy in proxies {
+            let mut reverse_proxy_handler = json!({ "handler": "reverse_proxy" });
+            let mut transport = serde_json::Map::new();
+            transport.insert("protocol".to_string(), json!("http"));
+            if let Some(upstream_env) = proxy_upstream_env(&proxy.upstream) {
+                reverse_proxy_handler["upstream_url"] = json!(format!("{{env.{upstream_env}}}"));
+            } else {
+                let (tls_enabled, dial) = parse_proxy_upstream(&proxy.upstream)?;
+                if tls_enabled {
+                    transport.insert("tls".to_string(), json!({}));
+                }
+                reverse_proxy_handler["upstreams"] = json!([{ "dial": dial }]);
+            }
+            for (name, timeout) in [
+                ("dial_timeout", &proxy.dial_timeout),
+                ("response_header_timeout", &proxy.response_header_timeout),
+            ] {
+                if let Some(timeout) = timeout {
+                    if !DURATION_REGEX.is_match(timeout) {
+                        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+                            format!(
+                                "proxies.{name} must be a duration like \"500ms\", \"10s\", or \"1m\", but got \"{timeout}\""
+                            ),
+                        ));
+                    }
+                    transport.insert(name.to_string(), json!(timeout));
+                }
+            }
+
+            reverse_proxy_handler["transport"] = json!(transport);
+            // Hosted backends, such as other Heroku apps, are routed by hostname, so the
+            // backend's own hostname is sent, unless another is configured.
+            let host_header = proxy
+                .host_header
+                .as_deref()
+                .unwrap_or("{http.reverse_proxy.upstream.host}");
+            reverse_proxy_handler["headers"] = json!({
+                "request": {
+                    "set": { "Host": [host_header] }
+                }
+            });
+
+            let mut handlers = vec![];
+            if let Some(ref strip_path_prefix) = proxy.strip_path_prefix {
+                handlers.push(json!({
+                    "handler": "rewrite",
+                    "strip_path_prefix": strip_path_prefix
+                }));
+            }
+            handlers.push(reverse_proxy_handler);
+
+            static_file_handlers.push(json!(
+            {
+                "handler": "subroute",
+                "routes": [{
+                    "match": [{ "path": [proxy.path_matcher] }],
+                    "handle": handlers,
+                    "terminal": true
+                }],
+            }));
+        }
+    }
+    Ok(())
+}
+
+// Each mount serves its prefix from its own document root, with the prefix stripped from the
+// request path, so that its files, index, SPA fallback & 404 page are found relative to its root.
+fn generate_mount_handlers(
+    config: &HerokuWebServerConfig,
+    static_file_handlers: &mut Vec<serde_json::Value>,
+) -> Result<(), StaticWebServerBuildpackError> {
+    let mounts = config.mounts.as_deref().unwrap_or_default();
+    tracing::info!({ CONFIG_MOUNTS_COUNT } = mounts.len(), "config");
+    for mount in mounts {
+        static_file_handlers.push(generate_mount_handler(mount)?);
+    }
+    Ok(())
+}
+
+fn generate_mount_handler(
+    mount: &MountConfig,
+) -> Result<serde_json::Value, StaticWebServerBuildpackError> {
+    let prefix = mount.path.trim_end_matches('/');
+    if !prefix.starts_with('/') || prefix.contains('*') {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!(
+            "mounts.path must be a URL path prefix other than /, like \"/docs\", but got \"{}\"",
+            mount.path
+        ),
+        ));
+    }
+    if let Some(status_code) = mount
+        .errors
+        .iter()
+        .flat_map(|errors| &errors.custom_pages)
+        .find(|(status_code, error_config)| **status_code != 404 || error_config.paths.is_some())
+        .map(|(status_code, _)| status_code)
+    {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(
+            format!(
+                "mounts.errors only supports the 404 status code, without paths, but got {status_code}"
+            ),
+        ));
+    }
+    let doc_root = mount.root.to_string_lossy().to_string();
+    let doc_index = mount
+        .index
+        .clone()
+        .unwrap_or(String::from(DEFAULT_DOC_INDEX));
+
+    let mut routes = vec![];
+    routes.extend(
+        mount
+            .spa_fallback
+            .as_ref()
+            .and_then(|v| generate_spa_fallback_route(v, &doc_root, &doc_index)),
+    );
+    routes.push(json!({
+        "handle": [{
+            "handler": "file_server",
+            "root": doc_root,
+            "index_names": vec![&doc_index],
+            "pass_thru": true,
+        }]
+    }));
+    routes.extend(generate_error_404_route(
+        &doc_root,
+        &doc_index,
+        mount.errors.as_ref(),
+        &HtmlFilePolicies::new(),
+    ));
+
+    // Without the trailing slash, the mount's relative URLs would resolve against its parent.
+    Ok(json!(
+    {
+        "handler": "subroute",
+        "routes": [
+            {
+                "match": [{ "path": [prefix] }],
+                "handle": generate_redirect_handlers(&format!("{prefix}/"), 301, true),
+                "terminal": true
+            },
+            {
+                "match": [{ "path": [format!("{prefix}/*")] }],
+                "handle": [
+                    {
+                        "handler": "rewrite",
+                        "strip_path_prefix": prefix
+                    },
+                    {
+                        "handler": "subroute",
+                        "routes": routes
+                    }
+                ],
+                "terminal": true
+            }
+        ],
+    }))
+}
+
+// Serves the fallback document for paths that are neither files nor directories in the
+// document root, so that client-side routes respond with the app. The fallback must be
+// revalidated, like other HTML, unless its Cache-Control is configured.
+fn generate_spa_fallback_route(
+    spa_fallback: &SpaFallbackConfig,
+    doc_root: &str,
+    doc_index: &str,
+) -> Option<serde_json::Value> {
+    if !spa_fallback.is_enabled() {
+        return None;
+    }
+    let opts = spa_fallback.opts();
+    let fallback_path = format!(
+        "/{}",
+        opts.and_then(|v| v.file_path.as_ref())
+            .map_or(doc_index.into(), |v| v.to_string_lossy())
+            .trim_start_matches('/')
+    );
+    let mut not_matchers = vec![json!({
+        "file": {
+            "root": doc_root,
+            "try_files": ["{http.request.uri.path}", "{http.request.uri.path}/"]
+        }
+    })];
+    if let Some(path_exclusions) = opts.and_then(|v| v.path_exclusions.as_ref()) {
+        not_matchers.push(json!({ "path": path_exclusions }));
+    }
+    Some(json!({
+        "match": [{
+            "not": not_matchers,
+            "file": {
+                "root": doc_root,
+                "try_files": [fallback_path]
+            }
+        }],
+        "handle": [
+            {
+                "handler": "rewrite",
+                "uri": fallback_path
+            },
+            {
+                "handler": "headers",
+                "response": {
+                    "set": {
+                        "Cache-Control": ["no-cache"]
+                    },
+                    "require": {
+                        "headers": {
+                            "Cache-Control": null
+                        }
+                    }
+                }
+            }
+        ]
+    }))
+}
+
+// An upstream that is only an env placeholder, like `{env.API_ORIGIN}`, holds a whole origin,
+// including the scheme, so it is split into TLS & dial address at launch, by caddy-config-env,
+// from the `upstream_url` field.
+pub(crate) fn proxy_upstream_env(upstream: &str) -> Option<&str> {
+    upstream
+        .strip_prefix("{env.")
+        .and_then(|v| v.strip_suffix('}'))
+        .filter(|name| is_env_name(name))
+}
+
+// Caddy dials upstreams by network address rather than URL, so the configured
+// upstream is split into whether TLS is used, and a `host:port` dial address.
+fn parse_proxy_upstream(upstream: &str) -> Result<(bool, String), StaticWebServerBuildpackError> {
+    let invalid_upstream = || {
+        StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+            "proxies.upstream must be an origin like \"https://api.example.com\", but got \"{upstream}\""
+        ))
+    };
+
+    let (tls_enabled, authority) = if let Some(authority) = upstream.strip_prefix("https://") {
+        (true, authority)
+    } else if let Some(authority) = upstream.strip_prefix("http://") {
+        (false, authority)
+    } else {
+        return Err(invalid_upstream());
+    };
+    let authority = authority.trim_end_matches('/');
+    if authority.is_empty() || authority.contains('/') {
+        return Err(invalid_upstream());
+    }
+
+    // Placeholders such as {env.API_HOST} are only expanded at runtime,
+    // so look for an explicit port outside of them.
+    let literal_authority = PLACEHOLDER_REGEX.replace_all(authority, "");
+    let has_port = match literal_authority.rfind(']') {
+        Some(ipv6_end) => literal_authority[ipv6_end..].contains(':'),
+        None => literal_authority.contains(':'),
+    };
+
+    if has_port {
+        Ok((tls_enabled, authority.to_string()))
+    } else if tls_enabled {
+        Ok((tls_enabled, format!("{authority}:443")))
+    } else {
+        Ok((tls_enabled, format!("{authority}:80")))
+    }
+}
+
+// Fingerprinted files never change content at the same URL, so they may be cached forever.
+// HTML documents reference those files, so they must be revalidated to pick up new deploys.
+fn generate_caching_routes(fingerprinted_paths: &[String]) -> Vec<serde_json::Value> {
+    let mut routes = vec![];
+    if !fingerprinted_paths.is_empty() {
+        routes.push(json!({
+            "match": [{
+                "path": fingerprinted_paths
+            }],
+            "handle": [{
+                "handler": "headers",
+                "response": {
+                    "set": {
+                        "Cache-Control": ["public, max-age=31536000, immutable"]
+                    }
+                }
+            }]
+        }));
+    }
+    routes.push(json!({
+        "handle": [{
+            "handler": "headers",
+            "response": {
+                "set": {
+                    "Cache-Control": ["no-cache"]
+                },
+                "require": {
+                    "headers": {
+                        "Content-Type": ["text/html*"],
+                        "Cache-Control": null
+                    }
+                }
+            }
+        }]
+    }));
+    routes
+}
+
+const RECOMMENDED_SECURITY_HEADERS: &[(&str, &str)] = &[
+    ("X-Content-Type-Options", "nosniff"),
+    ("Referrer-Policy", "strict-origin-when-cross-origin"),
+    ("X-Frame-Options", "SAMEORIGIN"),
+    (
+        "Permissions-Policy",
+        "camera=(), geolocation=(), microphone=(), payment=(), usb=()",
+    ),
+    ("Cross-Origin-Opener-Policy", "same-origin-allow-popups"),
+];
+
+const STRICT_SECURITY_HEADERS: &[(&str, &str)] = &[
+    ("X-Content-Type-Options", "nosniff"),
+    ("Referrer-Policy", "no-referrer"),
+    ("X-Frame-Options", "DENY"),
+    (
+        "Permissions-Policy",
+        "accelerometer=(), autoplay=(), camera=(), display-capture=(), encrypted-media=(), fullscreen=(), geolocation=(), gyroscope=(), magnetometer=(), microphone=(), midi=(), payment=(), picture-in-picture=(), publickey-credentials-get=(), screen-wake-lock=(), sync-xhr=(), usb=(), xr-spatial-tracking=()",
+    ),
+    ("Cross-Origin-Opener-Policy", "same-origin"),
+    ("Cross-Origin-Resource-Policy", "same-origin"),
+];
+
+fn generate_response_headers_routes(
+    headers: &[PathMatchedHeader],
+    security_headers: SecurityHeadersPreset,
+) -> Vec<serde_json::Value> {
+    let mut routes = vec![];
+
+    // The security headers preset comes first, so that configured headers may override it.
+    let preset_headers = match security_headers {
+        SecurityHeadersPreset::Recommended => RECOMMENDED_SECURITY_HEADERS,
+        SecurityHeadersPreset::Strict => STRICT_SECURITY_HEADERS,
+        SecurityHeadersPreset::Off => &[],
+    };
+    if !preset_headers.is_empty() {
+        let preset_headers_as_hash_map = preset_headers
+            .iter()
+            .map(|(key, value)| ((*key).to_string(), vec![(*value).to_string()]))
+            .collect::<HashMap<_, _>>();
+        routes.push(json!({
+            "match": [{
+                "path": ["*"]
+            }],
+            "handle": [{
+                "handler": "headers",
+                "response": {
+                    "set": preset_headers_as_hash_map
+                }
+            }]
+        }));
+    }
+
+    // Group headers with the same matchers while preserving the order of the matchers
+    // by "when-first-seen".
+    let mut groups = IndexMap::<(&String, &HeaderMatcher), Vec<&PathMatchedHeader>>::new();
+    for header in headers {
+        groups
+            .entry((&header.path_matcher, &header.matcher))
+            .or_default()
+            .push(header);
+    }
+
+    routes.extend(
+        groups
+            .into_iter()
+            .map(|((path_matcher, matcher), headers)| {
+                let mut handler = json!({ "handler": "headers" });
+                for (target, target_name) in [
+                    (HeaderTarget::Request, "request"),
+                    (HeaderTarget::Response, "response"),
+                ] {
+                    let target_headers = headers
+                        .iter()
+                        .filter(|header| header.target == target)
+                        .copied()
+                        .collect::<Vec<_>>();
+                    if !target_headers.is_empty() {
+                        handler[target_name] = generate_header_operations(&target_headers);
+                    }
+                }
+                if let Some(require) = generate_header_response_matcher(matcher) {
+                    handler["response"]["require"] = require;
+                    handler["response"]["deferred"] = json!(true);
+                }
+
+                let mut route_matcher = json!({ "path": vec![path_matcher] });
+                if let Some(ref host) = matcher.host {
+                    route_matcher["host"] = json!(host);
+                }
+                json!({
+                    "match": [route_matcher],
+                    "handle": [handler]
+                })
+            }),
+    );
+    routes
+}
+
+// Content types match the media type, ignoring parameters, such as `text/html; charset=utf-8`.
+fn generate_header_response_matcher(matcher: &HeaderMatcher) -> Option<serde_json::Value> {
+    if matcher.content_type.is_none() && matcher.status.is_none() {
+        return None;
+    }
+    let mut require = json!({});
+    if let Some(ref content_type) = matcher.content_type {
+        let content_type = content_type
+            .iter()
+            .map(|v| {
+                if v.ends_with('*') {
+                    v.clone()
+                } else {
+                    format!("{v}*")
+                }
+            })
+            .collect::<Vec<_>>();
+        require["headers"] = json!({ "Content-Type": content_type });
+    }
+    if let Some(ref status) = matcher.status {
+        require["status_code"] = json!(status);
+    }
+    Some(require)
+}
+
+fn generate_header_operations(headers: &[&PathMatchedHeader]) -> serde_json::Value {
+    let mut set_headers = HashMap::<String, Vec<String>>::new();
+    let mut add_headers = HashMap::<String, Vec<String>>::new();
+    let mut deleted_headers = vec![];
+    for header in headers {
+        match header.operation {
+            // An empty value removes the header, such as one set by the security headers preset.
+            HeaderOperation::Set if header.value.is_empty() => {
+                deleted_headers.push(header.key.clone());
+            }
+            HeaderOperation::Set => {
+                set_headers.insert(header.key.clone(), vec![header.value.clone()]);
+            }
+            HeaderOperation::Add => {
+                add_headers
+                    .entry(header.key.clone())
+                    .or_default()
+                    .push(header.value.clone());
+            }
+            HeaderOperation::Delete => deleted_headers.push(header.key.clone()),
+        }
+    }
+
+    let mut operations = json!({});
+    if !set_headers.is_empty() {
+        operations["set"] = json!(set_headers);
+    }
+    if !add_headers.is_empty() {
+        operations["add"] = json!(add_headers);
+    }
+    if !deleted_headers.is_empty() {
+        operations["delete"] = json!(deleted_headers);
+    }
+    operations
+}
+
+fn generate_error_404_route(
+    doc_root: &str,
+    doc_index: &str,
+    errors: Option<&ErrorsConfig>,
+    html_file_policies: &HtmlFilePolicies,
+) -> Vec<serde_json::Value> {
+    let error_config = errors.and_then(|errors| errors.custom_pages.get(&404));
+
+    let custom_handlers = error_config.map(|error_config| {
+        let status_code = error_config.status.unwrap_or(404);
+        tracing::info!(
+            { CONFIG_ERROR_404_FILE_PATH } = error_config.file_path.to_string_lossy().to_string(),
+            "config"
+        );
+        tracing::info!(
+            { CONFIG_ERROR_404_STATUS_CODE } = status_code.to_string(),
+            "config"
+        );
+        generate_custom_error_handlers(
+            doc_root,
+            doc_index,
+            error_config,
+            status_code,
+            html_file_policies,
+        )
+    });
+
+    generate_error_page_routes(
+        None,
+        custom_handlers,
+        Some(generate_default_error_handlers(404, DEFAULT_404_HTML)),
+        error_config.and_then(|ec| ec.path_exclusions.as_ref()),
+    )
+}
+
+// Routes for Caddy's error handling (`handle_errors`), which runs when a handler fails
+// with an HTTP error status, such as basic auth 401, or a reverse proxy's 502.
+// Not found 404 is instead handled by generate_error_404_route, after the file server.
+fn generate_error_routes(
+    doc_root: &str,
+    doc_index: &str,
+    errors: Option<&ErrorsConfig>,
+    html_file_policies: &HtmlFilePolicies,
+) -> Vec<serde_json::Value> {
+    let custom_pages = errors.map(|errors| &errors.custom_pages);
+    tracing::info!(
+        { CONFIG_ERROR_PAGES_STATUS_CODES } = custom_pages
+            .map(|pages| {
+                pages
+                    .keys()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .unwrap_or_default(),
+        "config"
+    );
+
+    // Other error statuses are only responded with for the `paths` listed with their pages.
+    let status_codes = DEFAULT_ERROR_PAGES
+        .iter()
+        .map(|(status_code, _)| *status_code)
+        .chain(
+            custom_pages
+                .into_iter()
+                .flatten()
+                .filter(|(_, error_config)| error_config.paths.is_some())
+                .map(|(status_code, _)| *status_code),
+        )
+        .collect::<BTreeSet<_>>();
+
+    status_codes
+        .into_iter()
+        .flat_map(|status_code| {
+            let error_config = custom_pages.and_then(|pages| pages.get(&status_code));
+            let status_matcher = json!({
+                "expr": format!("{{http.error.status_code}} == {status_code}"),
+                "name": format!("error_{status_code}")
+            });
+            let default_html = if status_code == 404 {
+                Some(DEFAULT_404_HTML)
+            } else {
+                DEFAULT_ERROR_PAGES
+                    .iter()
+                    .find(|(default_status_code, _)| *default_status_code == status_code)
+                    .map(|(_, html)| *html)
+            };
+            generate_error_page_routes(
+                Some(&status_matcher),
+                error_config.map(|error_config| {
+                    generate_custom_error_handlers(
+                        doc_root,
+                        doc_index,
+                        error_config,
+                        error_config.status.unwrap_or(status_code),
+                        html_file_policies,
+                    )
+                }),
+                default_html.map(|html| generate_default_error_handlers(status_code, html)),
+                error_config.and_then(|ec| ec.path_exclusions.as_ref()),
+            )
+        })
+        .collect()
+}
+
+// Responds with an error status for the `paths` listed with custom error pages, such as
+// 410 Gone for removed pages, which is then served by the error routes.
+fn generate_error_status_handler(errors: Option<&ErrorsConfig>) -> Option<serde_json::Value> {
+    let routes = errors
+        .into_iter()
+        .flat_map(|errors| &errors.custom_pages)
+        .filter_map(|(status_code, error_config)| {
+            error_config.paths.as_ref().map(|paths| {
+                json!({
+                    "match": [{ "path": paths }],
+                    "handle": [{
+                        "handler": "error",
+                        "status_code": status_code.to_string()
+                    }]
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    (!routes.is_empty()).then(|| {
+        json!({
+            "handler": "subroute",
+            "routes": routes
+        })
+    })
+}
+
+// Respond with the custom error page, unless the request path is excluded,
+// falling back to the default error page.
+fn generate_error_page_routes(
+    status_matcher: Option<&serde_json::Value>,
+    custom_handlers: Option<serde_json::Value>,
+    default_handlers: Option<serde_json::Value>,
+    path_exclusions: Option<&Vec<String>>,
+) -> Vec<serde_json::Value> {
+    let route = |exclusions: Option<&Vec<String>>, handlers: serde_json::Value| {
+        let mut matcher = serde_json::Map::new();
+        if let Some(status_matcher) = status_matcher {
+            matcher.insert("expression".to_string(), status_matcher.clone());
+        }
+        if let Some(exclusions) = exclusions {
+            matcher.insert("not".to_string(), json!([{"path": exclusions}]));
+        }
+        if matcher.is_empty() {
+            json!({ "handle": handlers })
+        } else {
+            json!({ "match": [matcher], "handle": handlers })
+        }
+    };
+
+    match (custom_handlers, default_handlers) {
+        (Some(custom), Some(default)) if path_exclusions.is_some() => {
+            vec![route(path_exclusions, custom), route(None, default)]
+        }
+        (Some(custom), _) => vec![route(path_exclusions, custom)],
+        (None, Some(default)) => vec![route(None, default)],
+        (None, None) => vec![],
+    }
+}
+
+fn generate_custom_error_handlers(
+    doc_root: &str,
+    doc_index: &str,
+    error_config: &ErrorConfig,
+    status_code: u16,
+    html_file_policies: &HtmlFilePolicies,
+) -> serde_json::Value {
+    let mut headers = json!({
+        "Cache-Control": ["no-store, no-cache, must-revalidate"],
+        "Pragma": ["no-cache"]
+    });
+    let error_url_path = format!(
+        "/{}",
+        error_config
+            .file_path
+            .to_string_lossy()
+            .trim_start_matches('/')
+    );
+    let html_file_policy = html_file_policies.get(&error_url_path);
+    if let Some(html_file_policy) = html_file_policy {
+        headers[html_file_policy.header_name] = json!([html_file_policy.policy]);
+    }
+
+    let mut handlers = vec![
+        json!({
+            "handler": "rewrite",
+            "uri": error_config.file_path,
+        }),
+        json!({
+            "handler": "headers",
+            "response": {
+                "set": headers
+            }
+        }),
+    ];
+    if html_file_policy.is_some_and(|v| v.nonce) {
+        handlers.push(generate_nonce_templates_handler());
+    }
+    handlers.push(json!({
+        "handler": "file_server",
+        "root": doc_root,
+        "status_code": status_code.to_string(),
+        "index_names": vec![doc_index],
+        "pass_thru": false
+    }));
+    json!(handlers)
+}
+
+fn generate_default_error_handlers(status_code: u16, html: &str) -> serde_json::Value {
+    json!([{
+        "handler": "static_response",
+        "status_code": status_code.to_string(),
+        "headers": {
+            "Content-Type": ["text/html"],
+            "Cache-Control": ["no-store, no-cache, must-revalidate"],
+            "Pragma": ["no-cache"]
+        },
+        "body": html
+    }])
+}
+
+macro_rules! default_error_html {
+    ($title:literal) => {
+        concat!(
+            r#"
+    <!DOCTYPE html>
+    <html lang="en">
+    <head>
+        <meta charset="utf-8">
+        <title>"#,
+            $title,
+            r#"</title>
+    </head>
+    <body>
+        <h1>"#,
+            $title,
+            r#"</h1>
+    </body>
+    </html>
+"#
+        )
+    };
+}
+
+const DEFAULT_401_HTML: &str = default_error_html!("401 Unauthorized");
+const DEFAULT_403_HTML: &str = default_error_html!("403 Forbidden");
+const DEFAULT_404_HTML: &str = default_error_html!("404 Not Found");
+const DEFAULT_500_HTML: &str = default_error_html!("500 Internal Server Error");
+const DEFAULT_502_HTML: &str = default_error_html!("502 Bad Gateway");
+const DEFAULT_503_HTML: &str = default_error_html!("503 Service Unavailable");
+const DEFAULT_504_HTML: &str = default_error_html!("504 Gateway Timeout");
+
+// Error pages served when a handler fails with the status code, unless customized.
+const DEFAULT_ERROR_PAGES: &[(u16, &str)] = &[
+    (401, DEFAULT_401_HTML),
+    (403, DEFAULT_403_HTML),
+    (500, DEFAULT_500_HTML),
+    (502, DEFAULT_502_HTML),
+    (503, DEFAULT_503_HTML),
+    (504, DEFAULT_504_HTML),
+];
+
+#[derive(Serialize)]
+struct StaticResponseHandler {
+    handler: String,
+    status_code: u16,
+    headers: Option<HashMap<String, Vec<String>>>,
+    body: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::heroku_web_server_config::{
+        CaddyBasicAuthOpts, CaddyServerOpts, CaddyStaticResponseConfig, Header, HstsConfig,
+        ProxyConfig, RedirectConfig, RewriteConfig, SpaFallbackOpts, SERVER_ERROR_STATUS_CODES,
+    };
+    use crate::StaticWebServerBuildpackError;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn generates_matched_response_headers_routes() {
+        let heroku_config = HerokuWebServerConfig {
+            headers: Some(vec![
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    key: String::from("X-Foo"),
+                    value: String::from("Bar"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*.html"),
+                    key: String::from("X-Baz"),
+                    value: String::from("Buz"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    key: String::from("X-Zuu"),
+                    value: String::from("Zem"),
+                    ..PathMatchedHeader::default()
+                },
+            ]),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let routes = generate_response_headers_routes(
+            &heroku_config.headers.unwrap(),
+            SecurityHeadersPreset::Off,
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"headers","response":{"set":{"X-Foo":["Bar"],"X-Zuu":["Zem"]}}}],"match":[{"path":["*"]}]}),
+                json!({"handle":[{"handler":"headers","response":{"set":{"X-Baz":["Buz"]}}}],"match":[{"path":["*.html"]}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_header_operations_routes() {
+        let header = |target, operation, key: &str, value: &str| PathMatchedHeader {
+            path_matcher: String::from("*"),
+            target,
+            operation,
+            key: key.to_string(),
+            value: value.to_string(),
+            ..PathMatchedHeader::default()
+        };
+        let routes = generate_response_headers_routes(
+            &[
+                header(HeaderTarget::Response, HeaderOperation::Set, "X-Foo", "Bar"),
+                header(
+                    HeaderTarget::Response,
+                    HeaderOperation::Add,
+                    "Link",
+                    "</app.css>; rel=preload",
+                ),
+                header(
+                    HeaderTarget::Response,
+                    HeaderOperation::Add,
+                    "Link",
+                    "</app.js>; rel=preload",
+                ),
+                header(
+                    HeaderTarget::Response,
+                    HeaderOperation::Delete,
+                    "Server",
+                    "",
+                ),
+                header(
+                    HeaderTarget::Request,
+                    HeaderOperation::Set,
+                    "X-Forwarded-Prefix",
+                    "/docs",
+                ),
+                header(
+                    HeaderTarget::Request,
+                    HeaderOperation::Delete,
+                    "X-Debug",
+                    "",
+                ),
+            ],
+            SecurityHeadersPreset::Off,
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"headers","request":{"set":{"X-Forwarded-Prefix":["/docs"]},"delete":["X-Debug"]},"response":{"set":{"X-Foo":["Bar"]},"add":{"Link":["</app.css>; rel=preload","</app.js>; rel=preload"]},"delete":["Server"]}}],"match":[{"path":["*"]}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_header_matcher_routes() {
+        let routes = generate_response_headers_routes(
+            &[
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    matcher: HeaderMatcher {
+                        host: Some(vec![String::from("www.example.com")]),
+                        ..HeaderMatcher::default()
+                    },
+                    key: String::from("X-Robots-Tag"),
+                    value: String::from("index"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    matcher: HeaderMatcher {
+                        content_type: Some(vec![
+                            String::from("text/html"),
+                            String::from("image/*"),
+                        ]),
+                        status: Some(vec![200]),
+                        ..HeaderMatcher::default()
+                    },
+                    key: String::from("Cache-Control"),
+                    value: String::from("no-cache"),
+                    ..PathMatchedHeader::default()
+                },
+            ],
+            SecurityHeadersPreset::Off,
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"headers","response":{"set":{"X-Robots-Tag":["index"]}}}],"match":[{"host":["www.example.com"],"path":["*"]}]}),
+                json!({"handle":[{"handler":"headers","response":{"deferred":true,"require":{"headers":{"Content-Type":["text/html*","image/*"]},"status_code":[200]},"set":{"Cache-Control":["no-cache"]}}}],"match":[{"path":["*"]}]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_global_response_headers_routes() {
+        let heroku_config = HerokuWebServerConfig {
+            headers: Some(vec![PathMatchedHeader {
+                path_matcher: String::from("*"),
+                key: String::from("X-Foo"),
+                value: String::from("Bar"),
+                ..PathMatchedHeader::default()
+            }]),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let routes = generate_response_headers_routes(
+            &heroku_config.headers.unwrap(),
+            SecurityHeadersPreset::Off,
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle": [{"handler":"headers","response":{"set":{"X-Foo":["Bar"]}}}],"match":[{"path":["*"]}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_security_headers_routes() {
+        let routes = generate_response_headers_routes(&[], SecurityHeadersPreset::Recommended);
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"headers","response":{"set":{"Cross-Origin-Opener-Policy":["same-origin-allow-popups"],"Permissions-Policy":["camera=(), geolocation=(), microphone=(), payment=(), usb=()"],"Referrer-Policy":["strict-origin-when-cross-origin"],"X-Content-Type-Options":["nosniff"],"X-Frame-Options":["SAMEORIGIN"]}}}],"match":[{"path":["*"]}]})
+            ]
+        );
+
+        let routes = generate_response_headers_routes(&[], SecurityHeadersPreset::Strict);
+        assert_eq!(routes.len(), 1);
+        assert_eq!(
+            routes[0]["handle"][0]["response"]["set"]["X-Frame-Options"],
+            json!(["DENY"])
+        );
+
+        assert!(generate_response_headers_routes(&[], SecurityHeadersPreset::Off).is_empty());
+    }
+
+    #[test]
+    fn generates_security_headers_routes_with_overrides() {
+        let routes = generate_response_headers_routes(
+            &[
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    key: String::from("X-Frame-Options"),
+                    value: String::from("DENY"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("/embed/*"),
+                    key: String::from("X-Frame-Options"),
+                    value: String::new(),
+                    ..PathMatchedHeader::default()
+                },
+            ],
+            SecurityHeadersPreset::Recommended,
+        );
+
+        assert_eq!(routes.len(), 3);
+        assert_eq!(
+            routes[1],
+            json!({"handle":[{"handler":"headers","response":{"set":{"X-Frame-Options":["DENY"]}}}],"match":[{"path":["*"]}]})
+        );
+        assert_eq!(
+            routes[2],
+            json!({"handle":[{"handler":"headers","response":{"delete":["X-Frame-Options"]}}],"match":[{"path":["/embed/*"]}]})
+        );
+    }
+
+    #[test]
+    fn merges_inline_sources_into_policy() {
+        let hashes = InlineHashes {
+            scripts: BTreeSet::from(["'sha256-abc='".to_string()]),
+            styles: BTreeSet::from(["'sha256-def='".to_string()]),
+        };
+
+        assert_eq!(
+            merge_inline_sources("default-src 'self'; img-src 'self' data:", &hashes),
+            "default-src 'self'; img-src 'self' data:; script-src 'self' 'sha256-abc='; style-src 'self' 'sha256-def='"
+        );
+        assert_eq!(
+            merge_inline_sources(
+                "default-src 'none'; script-src 'self' https://cdn.example.com; style-src 'none';",
+                &hashes
+            ),
+            "default-src 'none'; script-src 'self' https://cdn.example.com 'sha256-abc='; style-src 'sha256-def='"
+        );
+        assert_eq!(
+            merge_inline_sources("img-src 'self'", &hashes),
+            "img-src 'self'"
+        );
+        assert_eq!(
+            merge_inline_sources("default-src 'self'", &InlineHashes::default()),
+            "default-src 'self'"
+        );
+    }
+
+    #[test]
+    fn generates_content_security_policy_handlers() {
+        let inline_hashes = BTreeMap::from([
+            (
+                "/index.html".to_string(),
+                InlineHashes {
+                    scripts: BTreeSet::from(["'sha256-abc='".to_string()]),
+                    styles: BTreeSet::new(),
+                },
+            ),
+            ("/about.html".to_string(), InlineHashes::default()),
+            ("/docs/index.html".to_string(), InlineHashes::default()),
+        ]);
+        let html_file_policies = generate_html_file_policies(
+            Some(&ContentSecurityPolicyConfig {
+                policy: "default-src 'self'".to_string(),
+                report_only: None,
+                nonce: None,
+            }),
+            &inline_hashes,
+        );
+
+        let mut handlers = vec![];
+        generate_content_security_policy_handlers(&html_file_policies, "index.html", &mut handlers);
+        assert_eq!(
+            handlers,
+            vec![json!({"handler":"subroute","routes":[
+                {"match":[{"path":["/about.html","/docs/index.html","/docs/"]}],"handle":[{"handler":"headers","response":{"set":{"Content-Security-Policy":["default-src 'self'"]}}}]},
+                {"match":[{"path":["/index.html","/"]}],"handle":[{"handler":"headers","response":{"set":{"Content-Security-Policy":["default-src 'self'; script-src 'self' 'sha256-abc='"]}}}]}
+            ]})]
+        );
+
+        let routes = generate_error_404_route(
+            "public",
+            "index.html",
+            Some(&ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("index.html"),
+                        status: Some(200),
+                        path_exclusions: None,
+                        paths: None,
+                    },
+                )]),
+            }),
+            &html_file_policies,
+        );
+        assert_eq!(
+            routes[0]["handle"][1]["response"]["set"]["Content-Security-Policy"],
+            json!(["default-src 'self'; script-src 'self' 'sha256-abc='"])
+        );
+    }
+
+    #[test]
+    fn generates_report_only_content_security_policy() {
+        let html_file_policies = generate_html_file_policies(
+            Some(&ContentSecurityPolicyConfig {
+                policy: "default-src 'self'".to_string(),
+                report_only: Some(true),
+                nonce: None,
+            }),
+            &BTreeMap::from([("/index.html".to_string(), InlineHashes::default())]),
+        );
+        assert_eq!(
+            html_file_policies,
+            BTreeMap::from([(
+                "/index.html".to_string(),
+                HtmlFilePolicy {
+                    header_name: "Content-Security-Policy-Report-Only",
+                    policy: "default-src 'self'".to_string(),
+                    nonce: false,
+                }
+            )])
+        );
+
+        assert!(generate_html_file_policies(
+            None,
+            &BTreeMap::from([("/index.html".to_string(), InlineHashes::default())])
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn generates_nonce_content_security_policy_handlers() {
+        let inline_hashes = BTreeMap::from([
+            (
+                "/index.html".to_string(),
+                InlineHashes {
+                    scripts: BTreeSet::from(["'sha256-abc='".to_string()]),
+                    styles: BTreeSet::new(),
+                },
+            ),
+            ("/about.html".to_string(), InlineHashes::default()),
+        ]);
+        let html_file_policies = generate_html_file_policies(
+            Some(&ContentSecurityPolicyConfig {
+                policy: "default-src 'self'; style-src 'self'".to_string(),
+                report_only: None,
+                nonce: Some(true),
+            }),
+            &inline_hashes,
+        );
+
+        let mut handlers = vec![];
+        generate_content_security_policy_handlers(&html_file_policies, "index.html", &mut handlers);
+        assert_eq!(
+            handlers,
+            vec![json!({"handler":"subroute","routes":[
+                {"match":[{"path":["/about.html"]}],"handle":[{"handler":"headers","response":{"set":{"Content-Security-Policy":["default-src 'self'; style-src 'self'"]}}}]},
+                {"match":[{"path":["/index.html","/"]}],"handle":[
+                    {"handler":"headers","response":{"set":{"Content-Security-Policy":["default-src 'self'; style-src 'self' 'nonce-{http.request.uuid}'; script-src 'self' 'nonce-{http.request.uuid}'"]}}},
+                    {"handler":"templates","delimiters":["{{csp-nonce","}}"]}
+                ]}
+            ]})]
+        );
+
+        let routes = generate_error_404_route(
+            "public",
+            "index.html",
+            Some(&ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("index.html"),
+                        status: Some(200),
+                        path_exclusions: None,
+                        paths: None,
+                    },
+                )]),
+            }),
+            &html_file_policies,
+        );
+        assert_eq!(
+            routes[0]["handle"][2],
+            json!({"handler":"templates","delimiters":["{{csp-nonce","}}"]})
+        );
+    }
+
+    #[test]
+    fn generates_caching_routes() {
+        let routes = generate_caching_routes(&[
+            "/assets/index-B2x_9Abc.js".to_string(),
+            "/assets/index-Dq4k7bTE.css".to_string(),
+        ]);
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"match":[{"path":["/assets/index-B2x_9Abc.js","/assets/index-Dq4k7bTE.css"]}],"handle":[{"handler":"headers","response":{"set":{"Cache-Control":["public, max-age=31536000, immutable"]}}}]}),
+                json!({"handle":[{"handler":"headers","response":{"set":{"Cache-Control":["no-cache"]},"require":{"headers":{"Content-Type":["text/html*"],"Cache-Control":null}}}}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_caching_routes_without_fingerprinted_files() {
+        let routes = generate_caching_routes(&[]);
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"headers","response":{"set":{"Cache-Control":["no-cache"]},"require":{"headers":{"Content-Type":["text/html*"],"Cache-Control":null}}}}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_custom_404_error_route() {
+        let doc_root = String::from("tests/fixtures/custom_errors/public");
+        let doc_index = "index.html".to_string();
+
+        let heroku_config = HerokuWebServerConfig {
+            errors: Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("error-404.html"),
+                        status: None,
+                        path_exclusions: None,
+                        paths: None,
+                    },
+                )]),
+            }),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let routes = generate_error_404_route(
+            &doc_root,
+            &doc_index,
+            heroku_config.errors.as_ref(),
+            &HtmlFilePolicies::new(),
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"rewrite","uri":"error-404.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/custom_errors/public","status_code":"404"}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_custom_404_to_200_error_route() {
+        let doc_root = String::from("tests/fixtures/client_side_routing/public");
+        let doc_index = "index.html".to_string();
+
+        let heroku_config = HerokuWebServerConfig {
+            errors: Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("index.html"),
+                        status: Some(200),
+                        path_exclusions: None,
+                        paths: None,
+                    },
+                )]),
+            }),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let routes = generate_error_404_route(
+            &doc_root,
+            &doc_index,
+            heroku_config.errors.as_ref(),
+            &HtmlFilePolicies::new(),
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"handle":[{"handler":"rewrite","uri":"index.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/client_side_routing/public","status_code":"200"}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_custom_404_error_route_with_path_exclusions() {
+        let doc_root = String::from("tests/fixtures/client_side_routing/public");
+        let doc_index = "index.html".to_string();
+
+        let heroku_config = HerokuWebServerConfig {
+            errors: Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("index.html"),
+                        status: Some(200),
+                        path_exclusions: Some(vec!["/assets/*".to_string()]),
+                        paths: None,
+                    },
+                )]),
+            }),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let routes = generate_error_404_route(
+            &doc_root,
+            &doc_index,
+            heroku_config.errors.as_ref(),
+            &HtmlFilePolicies::new(),
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"match":[{"not":[{"path":["/assets/*"]}]}],"handle":[{"handler":"rewrite","uri":"index.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/client_side_routing/public","status_code":"200"}]}),
+                json!({"handle":[{"body":"\n    <!DOCTYPE html>\n    <html lang=\"en\">\n    <head>\n        <meta charset=\"utf-8\">\n        <title>404 Not Found</title>\n    </head>\n    <body>\n        <h1>404 Not Found</h1>\n    </body>\n    </html>\n","handler":"static_response","headers":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Content-Type":["text/html"],"Pragma":["no-cache"]},"status_code":"404"}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_custom_404_error_route_with_multiple_path_exclusions() {
+        let doc_root = String::from("tests/fixtures/client_side_routing/public");
+        let doc_index = "index.html".to_string();
+
+        let heroku_config = HerokuWebServerConfig {
+            errors: Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("index.html"),
+                        status: Some(200),
+                        path_exclusions: Some(vec![
+                            "/assets/*".to_string(),
+                            "/static/*".to_string(),
+                        ]),
+                        paths: None,
+                    },
+                )]),
+            }),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let routes = generate_error_404_route(
+            &doc_root,
+            &doc_index,
+            heroku_config.errors.as_ref(),
+            &HtmlFilePolicies::new(),
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"match":[{"not":[{"path":["/assets/*","/static/*"]}]}],"handle":[{"handler":"rewrite","uri":"index.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/client_side_routing/public","status_code":"200"}]}),
+                json!({"handle":[{"body":"\n    <!DOCTYPE html>\n    <html lang=\"en\">\n    <head>\n        <meta charset=\"utf-8\">\n        <title>404 Not Found</title>\n    </head>\n    <body>\n        <h1>404 Not Found</h1>\n    </body>\n    </html>\n","handler":"static_response","headers":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Content-Type":["text/html"],"Pragma":["no-cache"]},"status_code":"404"}]})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_default_error_routes() {
+        let routes = generate_error_routes("public", "index.html", None, &HtmlFilePolicies::new());
+        let default_status_codes = DEFAULT_ERROR_PAGES
+            .iter()
+            .map(|(status_code, _)| *status_code)
+            .chain([404])
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            default_status_codes,
+            SERVER_ERROR_STATUS_CODES.iter().copied().collect()
+        );
+
+        let status_codes = routes
+            .iter()
+            .map(|route| route["match"][0]["expression"]["expr"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            status_codes,
+            vec![
+                "{http.error.status_code} == 401",
+                "{http.error.status_code} == 403",
+                "{http.error.status_code} == 500",
+                "{http.error.status_code} == 502",
+                "{http.error.status_code} == 503",
+                "{http.error.status_code} == 504",
+            ]
+        );
+        assert_eq!(
+            routes[0],
+            json!({"match":[{"expression":{"expr":"{http.error.status_code} == 401","name":"error_401"}}],"handle":[{"body":"\n    <!DOCTYPE html>\n    <html lang=\"en\">\n    <head>\n        <meta charset=\"utf-8\">\n        <title>401 Unauthorized</title>\n    </head>\n    <body>\n        <h1>401 Unauthorized</h1>\n    </body>\n    </html>\n","handler":"static_response","headers":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Content-Type":["text/html"],"Pragma":["no-cache"]},"status_code":"401"}]})
+        );
+    }
+
+    #[test]
+    fn generates_custom_error_routes() {
+        let errors = ErrorsConfig {
+            custom_pages: BTreeMap::from([
+                (
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("error-404.html"),
+                        status: None,
+                        path_exclusions: None,
+                        paths: None,
+                    },
+                ),
+                (
+                    403,
+                    ErrorConfig {
+                        file_path: PathBuf::from("error-403.html"),
+                        status: None,
+                        path_exclusions: None,
+                        paths: None,
+                    },
+                ),
+                (
+                    410,
+                    ErrorConfig {
+                        file_path: PathBuf::from("error-410.html"),
+                        status: None,
+                        path_exclusions: None,
+                        paths: Some(vec!["/old-campaign/*".to_string()]),
+                    },
+                ),
+                (
+                    503,
+                    ErrorConfig {
+                        file_path: PathBuf::from("maintenance.html"),
+                        status: None,
+                        path_exclusions: Some(vec!["/api/*".to_string()]),
+                        paths: None,
+                    },
+                ),
+            ]),
+        };
+
+        let routes = generate_error_routes(
+            "public",
+            "index.html",
+            Some(&errors),
+            &HtmlFilePolicies::new(),
+        );
+
+        // 404 is handled after the file server, not as an error route.
+        assert_eq!(routes.len(), 8);
+        assert_eq!(
+            routes[1],
+            json!({"match":[{"expression":{"expr":"{http.error.status_code} == 403","name":"error_403"}}],"handle":[{"handler":"rewrite","uri":"error-403.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"public","status_code":"403"}]})
+        );
+        assert_eq!(
+            routes[2],
+            json!({"match":[{"expression":{"expr":"{http.error.status_code} == 410","name":"error_410"}}],"handle":[{"handler":"rewrite","uri":"error-410.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"public","status_code":"410"}]})
+        );
+        assert_eq!(
+            routes[5],
+            json!({"match":[{"expression":{"expr":"{http.error.status_code} == 503","name":"error_503"},"not":[{"path":["/api/*"]}]}],"handle":[{"handler":"rewrite","uri":"maintenance.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"public","status_code":"503"}]})
+        );
+        assert_eq!(
+            routes[6]["match"],
+            json!([{"expression":{"expr":"{http.error.status_code} == 503","name":"error_503"}}])
+        );
+        assert_eq!(routes[6]["handle"][0]["handler"], "static_response");
+
+        // Only the listed paths respond with 410, which the server never responds with itself.
+        assert_eq!(
+            generate_error_status_handler(Some(&errors)),
+            Some(
+                json!({"handler":"subroute","routes":[{"match":[{"path":["/old-campaign/*"]}],"handle":[{"handler":"error","status_code":"410"}]}]})
+            )
+        );
+        assert_eq!(generate_error_status_handler(None), None);
+    }
+
+    #[test]
+    fn generates_static_response_handlers() {
+        let heroku_config = HerokuWebServerConfig {
+            caddy_server_opts: Some(CaddyServerOpts {
+                static_responses: Some(vec![
+                    CaddyStaticResponseConfig {
+                        host_matcher: Some("original.example.com".to_string()),
+                        path_matcher: None,
+                        status: Some(301),
+                        headers: Some(vec![
+                            Header {
+                                key: "Location".to_string(),
+                                value: "https://new.example.com{http.request.uri}".to_string(),
+                            },
+                            Header {
+                                key: "X-Redirected-From".to_string(),
+                                value: "original.example.com".to_string(),
+                            },
+                        ]),
+                        body: None,
+                    },
+                    CaddyStaticResponseConfig {
+                        host_matcher: Some("original.example.com".to_string()),
+                        path_matcher: Some("/blog/*".to_string()),
+                        status: Some(301),
+                        headers: Some(vec![Header {
+                            key: "Location".to_string(),
+                            value:
+                                "https://{http.request.host}/new-blog/{http.request.uri.path.file}"
+                                    .to_string(),
+                        }]),
+                        body: None,
+                    },
+                    CaddyStaticResponseConfig {
+                        host_matcher: None,
+                        path_matcher: Some("/api/*".to_string()),
+                        status: Some(500),
+                        headers: Some(vec![Header {
+                            key: "Content-Type".to_string(),
+                            value: "application/json".to_string(),
+                        }]),
+                        body: Some(r#"{"error":"Service not available"}"#.to_string()),
+                    },
+                ]),
+                ..CaddyServerOpts::default()
+            }),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let mut handlers = vec![];
+        generate_static_response_handlers(&heroku_config, &mut handlers).unwrap();
+
+        assert_eq!(handlers.len(), 3);
+
+        assert_eq!(
+            handlers[0],
+            json!({
+                "handler": "subroute",
+                "routes": [{
+                    "match": [{"host": ["original.example.com"]}],
+                    "handle": [{
+                        "handler": "static_response",
+                        "status_code": 301,
+                        "headers": {
+                            "Location": ["https://new.example.com{http.request.uri}"],
+                            "X-Redirected-From": ["original.example.com"]
+                        },
+                        "body": null
+                    }],
+                    "terminal": true
+                }]
+            })
+        );
+
+        assert_eq!(
+            handlers[1],
+            json!({
+                "handler": "subroute",
+                "routes": [{
+                    "match": [{"host": ["original.example.com"]}, {"path": ["/blog/*"]}],
+                    "handle": [{
+                        "handler": "static_response",
+                        "status_code": 301,
+                        "headers": {"Location": ["https://{http.request.host}/new-blog/{http.request.uri.path.file}"]},
+                        "body": null
+                    }],
+                    "terminal": true
+                }]
+            })
+        );
+
+        assert_eq!(
+            handlers[2],
+            json!({
+                "handler": "subroute",
+                "routes": [{
+                    "match": [{"path": ["/api/*"]}],
+                    "handle": [{
+                        "handler": "static_response",
+                        "status_code": 500,
+                        "headers": {"Content-Type": ["application/json"]},
+                        "body": r#"{"error":"Service not available"}"#
+                    }],
+                    "terminal": true
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn generates_static_response_handlers_error_when_no_matchers() {
+        let heroku_config = HerokuWebServerConfig {
+            caddy_server_opts: Some(CaddyServerOpts {
+                static_responses: Some(vec![CaddyStaticResponseConfig {
+                    host_matcher: None,
+                    path_matcher: None,
+                    status: Some(200),
+                    headers: None,
+                    body: Some("test".to_string()),
+                }]),
+                ..CaddyServerOpts::default()
+            }),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let mut handlers = vec![];
+        let result = generate_static_response_handlers(&heroku_config, &mut handlers);
+
+        assert!(result.is_err());
+        if let Err(StaticWebServerBuildpackError::ConfigurationConstraint(msg)) = result {
+            assert_eq!(
+                msg,
+                "host_matcher or path_matcher must be set for caddy_server_opts.static_responses"
+            );
+        } else {
+            panic!("Expected ConfigurationConstraint error");
+        }
+    }
+
+    #[test]
+    fn generates_proxy_handlers() {
+        let heroku_config = HerokuWebServerConfig {
+            proxies: Some(vec![
+                ProxyConfig {
+                    path_matcher: "/api/*".to_string(),
+                    upstream: "https://{env.API_HOST}".to_string(),
+                    strip_path_prefix: Some("/api".to_string()),
+                    host_header: Some("{http.request.host}".to_string()),
+                    dial_timeout: Some("5s".to_string()),
+                    response_header_timeout: Some("30s".to_string()),
+                },
+                ProxyConfig {
+                    path_matcher: "/search".to_string(),
+                    upstream: "http://127.0.0.1:{env.PORT}".to_string(),
+                    strip_path_prefix: None,
+                    host_header: None,
+                    dial_timeout: None,
+                    response_header_timeout: None,
+                },
+                ProxyConfig {
+                    path_matcher: "/graphql".to_string(),
+                    upstream: "{env.GRAPHQL_ORIGIN}".to_string(),
+                    strip_path_prefix: None,
+                    host_header: None,
+                    dial_timeout: Some("5s".to_string()),
+                    response_header_timeout: None,
+                },
+            ]),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let mut handlers = vec![];
+        generate_proxy_handlers(&heroku_config, &mut handlers).unwrap();
+
+        assert_eq!(
+            handlers,
+            vec![
+                json!({
+                    "handler": "subroute",
+                    "routes": [{
+                        "match": [{"path": ["/api/*"]}],
+                        "handle": [
+                            {"handler": "rewrite", "strip_path_prefix": "/api"},
+                            {
+                                "handler": "reverse_proxy",
+                                "upstreams": [{"dial": "{env.API_HOST}:443"}],
+                                "transport": {
+                                    "protocol": "http",
+                                    "tls": {},
+                                    "dial_timeout": "5s",
+                                    "response_header_timeout": "30s"
+                                },
+                                "headers": {
+                                    "request": {
+                                        "set": {"Host": ["{http.request.host}"]}
+                                    }
+                                }
+                            }
+                        ],
+                        "terminal": true
+                    }]
+                }),
+                json!({
+                    "handler": "subroute",
+                    "routes": [{
+                        "match": [{"path": ["/search"]}],
+                        "handle": [{
+                            "handler": "reverse_proxy",
+                            "upstreams": [{"dial": "127.0.0.1:{env.PORT}"}],
+                            "transport": {"protocol": "http"},
+                            "headers": {
+                                "request": {
+                                    "set": {"Host": ["{http.reverse_proxy.upstream.host}"]}
+                                }
+                            }
+                        }],
+                        "terminal": true
+                    }]
+                }),
+                // The whole origin is split at launch, by caddy-config-env.
+                json!({
+                    "handler": "subroute",
+                    "routes": [{
+                        "match": [{"path": ["/graphql"]}],
+                        "handle": [{
+                            "handler": "reverse_proxy",
+                            "upstream_url": "{env.GRAPHQL_ORIGIN}",
+                            "transport": {"protocol": "http", "dial_timeout": "5s"},
+                            "headers": {
+                                "request": {
+                                    "set": {"Host": ["{http.reverse_proxy.upstream.host}"]}
+                                }
+                            }
+                        }],
+                        "terminal": true
+                    }]
+                })
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_proxy_handlers_errors() {
+        for (dial_timeout, response_header_timeout) in [
+            (Some("5"), None),
+            (Some("5 seconds"), None),
+            (None, Some("1m30")),
+            (None, Some("-1s")),
+        ] {
+            let heroku_config = HerokuWebServerConfig {
+                proxies: Some(vec![ProxyConfig {
+                    path_matcher: "/api/*".to_string(),
+                    upstream: "https://api.example.com".to_string(),
+                    dial_timeout: dial_timeout.map(String::from),
+                    response_header_timeout: response_header_timeout.map(String::from),
+                    ..ProxyConfig::default()
+                }]),
+                ..HerokuWebServerConfig::default()
+            };
+            let result = generate_proxy_handlers(&heroku_config, &mut vec![]);
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "{dial_timeout:?} {response_header_timeout:?}"
+            );
+        }
+
+        for timeout in ["0", "500ms", "1m30s", "1.5h", "2d"] {
+            assert!(DURATION_REGEX.is_match(timeout), "{timeout}");
+        }
+    }
+
+    #[test]
+    fn parses_proxy_upstreams() {
+        assert_eq!(
+            parse_proxy_upstream("https://api.example.com").unwrap(),
+            (true, "api.example.com:443".to_string())
+        );
+        assert_eq!(
+            parse_proxy_upstream("http://api.example.com/").unwrap(),
+            (false, "api.example.com:80".to_string())
+        );
+        assert_eq!(
+            parse_proxy_upstream("http://[::1]:8080").unwrap(),
+            (false, "[::1]:8080".to_string())
+        );
+        assert_eq!(
+            parse_proxy_upstream("https://{env.API_HOST}:8443").unwrap(),
+            (true, "{env.API_HOST}:8443".to_string())
+        );
+        assert!(parse_proxy_upstream("api.example.com").is_err());
+        assert!(parse_proxy_upstream("https://api.example.com/v1").is_err());
+        assert!(parse_proxy_upstream("https://").is_err());
+        assert!(parse_proxy_upstream("{env.API_ORIGIN}").is_err());
+    }
 
-    match (custom_handlers, path_exclusions) {
-        (Some(handlers), Some(exclusions)) => vec![
-            json!({
-                "match": [{"not": [{"path": exclusions}]}],
-                "handle": handlers
+    #[test]
+    fn parses_proxy_upstream_envs() {
+        assert_eq!(proxy_upstream_env("{env.API_ORIGIN}"), Some("API_ORIGIN"));
+        assert_eq!(proxy_upstream_env("https://{env.API_HOST}"), None);
+        assert_eq!(proxy_upstream_env("{env.API_HOST}:8443"), None);
+        assert_eq!(proxy_upstream_env("{env.API-ORIGIN}"), None);
+        assert_eq!(proxy_upstream_env("{http.request.host}"), None);
+    }
+
+    #[test]
+    fn generates_basic_auth_handler() {
+        let handler = generate_basic_auth_handler(&CaddyBasicAuthConfig::Enabled(true), &[]);
+
+        assert_eq!(
+            handler,
+            json!({"handler":"subroute","routes":[{"match":[{"expression":{"expr":"{env.WEB_BASIC_AUTH_DISABLED} != 'true'","name":"basic_auth_required"}}],"handle":[{"handler":"authentication","providers":{"http_basic":{"accounts":[{"username":"{env.WEB_BASIC_AUTH_USERNAME}","password":"{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"},{"username":"{env.WEB_BASIC_AUTH_USERS}"}],"realm":"Restricted"}}}]}]})
+        );
+    }
+
+    #[test]
+    fn generates_path_scoped_basic_auth_handler() {
+        let handler = generate_basic_auth_handler(
+            &CaddyBasicAuthConfig::Options(CaddyBasicAuthOpts {
+                paths: Some(vec!["/admin/*".to_string()]),
+                exclude_paths: Some(vec!["/admin/health".to_string()]),
+                ..CaddyBasicAuthOpts::default()
             }),
-            json!({
-                "handle": default_handlers
+            &[BasicAuthAccount {
+                username: "reviewer".to_string(),
+                password: "$2y$10$abc".to_string(),
+            }],
+        );
+
+        assert_eq!(
+            handler,
+            json!({"handler":"subroute","routes":[{"match":[{"expression":{"expr":"{env.WEB_BASIC_AUTH_DISABLED} != 'true'","name":"basic_auth_required"},"path":["/admin/*"],"not":[{"path":["/admin/health"]}]}],"handle":[{"handler":"authentication","providers":{"http_basic":{"accounts":[{"username":"{env.WEB_BASIC_AUTH_USERNAME}","password":"{env.WEB_BASIC_AUTH_PASSWORD_BCRYPT}"},{"username":"{env.WEB_BASIC_AUTH_USERS}"},{"username":"reviewer","password":"$2y$10$abc"}],"realm":"Restricted"}}}]}]})
+        );
+    }
+
+    #[test]
+    fn generates_forward_auth_handler() {
+        let handler = generate_forward_auth_handler(&ForwardAuthConfig {
+            url_env: Some("FORWARD_AUTH_URL".to_string()),
+            copy_headers: Some(vec!["Remote-User".to_string()]),
+            path_exclusions: Some(vec!["/health".to_string()]),
+        })
+        .unwrap();
+
+        assert_eq!(
+            handler,
+            json!({"handler":"subroute","routes":[{"match":[{"not":[{"path":["/health"]}]}],"handle":[{"handler":"reverse_proxy","forward_auth_url":"{env.FORWARD_AUTH_URL}","headers":{"request":{"set":{"X-Forwarded-Method":["{http.request.method}"],"X-Forwarded-Uri":["{http.request.uri}"]}}},"handle_response":[{"match":{"status_code":[2]},"routes":[{"handle":[{"handler":"headers","request":{"delete":["Remote-User"]}}]},{"match":[{"not":[{"vars":{"{http.reverse_proxy.header.Remote-User}":[""]}}]}],"handle":[{"handler":"headers","request":{"set":{"Remote-User":["{http.reverse_proxy.header.Remote-User}"]}}}]}]}]}]}]})
+        );
+    }
+
+    #[test]
+    fn generates_forward_auth_handler_errors() {
+        for forward_auth in [
+            ForwardAuthConfig::default(),
+            ForwardAuthConfig {
+                url_env: Some("https://auth.example.com".to_string()),
+                ..ForwardAuthConfig::default()
+            },
+            ForwardAuthConfig {
+                url_env: Some("FORWARD_AUTH_URL".to_string()),
+                copy_headers: Some(vec!["Remote User".to_string()]),
+                ..ForwardAuthConfig::default()
+            },
+        ] {
+            let result = generate_forward_auth_handler(&forward_auth);
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "should reject {forward_auth:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn generates_maintenance_route() {
+        let route = generate_maintenance_route(
+            &MaintenanceConfig::default(),
+            "public",
+            "index.html",
+            &HtmlFilePolicies::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            route,
+            json!({"match":[{"expression":{"expr":"{env.WEB_MAINTENANCE_MODE} == 'true'","name":"maintenance_mode"}}],"handle":[{"handler":"error","status_code":503}],"terminal":true})
+        );
+    }
+
+    #[test]
+    fn generates_maintenance_route_with_page_and_bypass() {
+        let route = generate_maintenance_route(
+            &MaintenanceConfig {
+                file_path: Some(PathBuf::from("maintenance.html")),
+                retry_after: Some(600),
+                bypass_ips: Some(vec!["203.0.113.0/24".to_string()]),
+                bypass_cookie: Some("maintenance_bypass".to_string()),
+                path_exclusions: Some(vec!["/maintenance/*".to_string()]),
+                ..MaintenanceConfig::default()
+            },
+            "public",
+            "index.html",
+            &HtmlFilePolicies::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            route,
+            json!({"match":[{"expression":{"expr":"{env.WEB_MAINTENANCE_MODE} == 'true'","name":"maintenance_mode"},"not":[{"client_ip":{"ranges":["203.0.113.0/24"]}},{"expression":{"expr":"{env.WEB_MAINTENANCE_BYPASS_TOKEN} != '' && {http.request.cookie.maintenance_bypass} == {env.WEB_MAINTENANCE_BYPASS_TOKEN}","name":"maintenance_bypass_cookie"}},{"path":["/maintenance/*"]}]}],"handle":[{"handler":"headers","response":{"set":{"Retry-After":["600"]}}},{"handler":"rewrite","uri":"maintenance.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","root":"public","status_code":"503","index_names":["index.html"],"pass_thru":false}],"terminal":true})
+        );
+    }
+
+    #[test]
+    fn generates_maintenance_route_errors() {
+        for maintenance in [
+            MaintenanceConfig {
+                status: Some(200),
+                ..MaintenanceConfig::default()
+            },
+            MaintenanceConfig {
+                bypass_ips: Some(vec!["not-an-ip".to_string()]),
+                ..MaintenanceConfig::default()
+            },
+            MaintenanceConfig {
+                bypass_cookie: Some("bypass cookie".to_string()),
+                ..MaintenanceConfig::default()
+            },
+        ] {
+            let result = generate_maintenance_route(
+                &maintenance,
+                "public",
+                "index.html",
+                &HtmlFilePolicies::new(),
+            );
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "should reject {maintenance:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn generates_spa_fallback_route() {
+        let route =
+            generate_spa_fallback_route(&SpaFallbackConfig::Enabled(true), "public", "app.html");
+
+        assert_eq!(
+            route,
+            Some(
+                json!({"handle":[{"handler":"rewrite","uri":"/app.html"},{"handler":"headers","response":{"require":{"headers":{"Cache-Control":null}},"set":{"Cache-Control":["no-cache"]}}}],"match":[{"file":{"root":"public","try_files":["/app.html"]},"not":[{"file":{"root":"public","try_files":["{http.request.uri.path}","{http.request.uri.path}/"]}}]}]})
+            )
+        );
+
+        for spa_fallback in [
+            SpaFallbackConfig::Enabled(false),
+            SpaFallbackConfig::Options(SpaFallbackOpts {
+                enabled: Some(false),
+                file_path: Some(PathBuf::from("index.html")),
+                ..SpaFallbackOpts::default()
             }),
-        ],
-        (Some(handlers), None) => vec![json!({
-            "handle": handlers
-        })],
-        _ => vec![json!({
-            "handle": default_handlers
-        })],
+        ] {
+            assert_eq!(
+                generate_spa_fallback_route(&spa_fallback, "public", "index.html"),
+                None
+            );
+        }
     }
-}
 
-const DEFAULT_404_HTML: &str = r#"
-    <!DOCTYPE html>
-    <html lang="en">
-    <head>
-        <meta charset="utf-8">
-        <title>404 Not Found</title>
-    </head>
-    <body>
-        <h1>404 Not Found</h1>
-    </body>
-    </html>
-"#;
+    #[test]
+    fn generates_content_etags_file_server() {
+        let file_server = |heroku_config: &HerokuWebServerConfig| {
+            let caddy_config =
+                caddy_json_config(heroku_config, &DocRootFiles::default(), &[]).unwrap();
+            caddy_config["apps"]["http"]["servers"]["public"]["routes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|route| route["handle"].as_array())
+                .flatten()
+                .find(|h| h["handler"] == "file_server")
+                .cloned()
+                .expect("should have the file server handler")
+        };
 
-#[derive(Serialize)]
-struct StaticResponseHandler {
-    handler: String,
-    status_code: u16,
-    headers: Option<HashMap<String, Vec<String>>>,
-    body: Option<String>,
-}
+        // Off by default, so that apps which already ship `*.etag` files keep serving them.
+        let handler = file_server(&HerokuWebServerConfig::default());
+        assert_eq!(handler.get("etag_file_extensions"), None);
+        assert_eq!(handler.get("hide"), None);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::heroku_web_server_config::{
-        CaddyServerOpts, CaddyStaticResponseConfig, ErrorConfig, ErrorsConfig, Header,
-    };
-    use crate::StaticWebServerBuildpackError;
-    use std::path::PathBuf;
+        let handler = file_server(&HerokuWebServerConfig {
+            content_etags: Some(true),
+            ..HerokuWebServerConfig::default()
+        });
+        assert_eq!(handler["etag_file_extensions"], json!([".etag"]));
+        assert_eq!(handler["hide"], json!(["*.etag"]));
+    }
 
     #[test]
-    fn generates_matched_response_headers_routes() {
+    fn generates_spa_fallback_before_file_server() {
         let heroku_config = HerokuWebServerConfig {
-            headers: Some(vec![
-                PathMatchedHeader {
-                    path_matcher: String::from("*"),
-                    key: String::from("X-Foo"),
-                    value: String::from("Bar"),
-                },
-                PathMatchedHeader {
-                    path_matcher: String::from("*.html"),
-                    key: String::from("X-Baz"),
-                    value: String::from("Buz"),
-                },
-                PathMatchedHeader {
-                    path_matcher: String::from("*"),
-                    key: String::from("X-Zuu"),
-                    value: String::from("Zem"),
-                },
-            ]),
+            spa_fallback: Some(SpaFallbackConfig::Options(SpaFallbackOpts {
+                file_path: Some(PathBuf::from("index.html")),
+                path_exclusions: Some(vec![String::from("/assets/*")]),
+                ..SpaFallbackOpts::default()
+            })),
+            ..HerokuWebServerConfig::default()
+        };
+        let caddy_config =
+            caddy_json_config(&heroku_config, &DocRootFiles::default(), &[]).unwrap();
+        let routes = caddy_config["apps"]["http"]["servers"]["public"]["routes"]
+            .as_array()
+            .unwrap();
+        let handlers = routes
+            .iter()
+            .filter_map(|route| route["handle"].as_array())
+            .find(|handlers| handlers.iter().any(|h| h["handler"] == "file_server"))
+            .expect("should have the file server route");
+
+        let file_server_index = handlers
+            .iter()
+            .position(|h| h["handler"] == "file_server")
+            .unwrap();
+        assert_eq!(
+            handlers[file_server_index - 1],
+            json!({"handler":"subroute","routes":[generate_spa_fallback_route(
+                heroku_config.spa_fallback.as_ref().unwrap(),
+                DEFAULT_DOC_ROOT,
+                DEFAULT_DOC_INDEX
+            )]})
+        );
+    }
+
+    #[test]
+    fn generates_mount_handler() {
+        let handler = generate_mount_handler(&MountConfig {
+            path: String::from("/docs/"),
+            root: PathBuf::from("docs/build"),
+            spa_fallback: Some(SpaFallbackConfig::Options(SpaFallbackOpts {
+                file_path: Some(PathBuf::from("index.html")),
+                path_exclusions: Some(vec![String::from("/assets/*")]),
+                ..SpaFallbackOpts::default()
+            })),
+            ..MountConfig::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            handler,
+            json!({"handler":"subroute","routes":[{"handle":[{"handler":"subroute","routes":[{"handle":[{"handler":"static_response","headers":{"Location":["/docs/?{http.request.uri.query}"]},"status_code":301}],"match":[{"expression":{"expr":"{http.request.uri.query} != ''","name":"redirect_query_present"}}]},{"handle":[{"handler":"static_response","headers":{"Location":["/docs/"]},"status_code":301}]}]}],"match":[{"path":["/docs"]}],"terminal":true},{"handle":[{"handler":"rewrite","strip_path_prefix":"/docs"},{"handler":"subroute","routes":[{"handle":[{"handler":"rewrite","uri":"/index.html"},{"handler":"headers","response":{"require":{"headers":{"Cache-Control":null}},"set":{"Cache-Control":["no-cache"]}}}],"match":[{"file":{"root":"docs/build","try_files":["/index.html"]},"not":[{"file":{"root":"docs/build","try_files":["{http.request.uri.path}","{http.request.uri.path}/"]}},{"path":["/assets/*"]}]}]},{"handle":[{"handler":"file_server","index_names":["index.html"],"pass_thru":true,"root":"docs/build"}]},{"handle":[{"body":DEFAULT_404_HTML,"handler":"static_response","headers":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Content-Type":["text/html"],"Pragma":["no-cache"]},"status_code":"404"}]}]}],"match":[{"path":["/docs/*"]}],"terminal":true}]})
+        );
+    }
+
+    #[test]
+    fn generates_mount_handler_errors() {
+        for path in ["/", "docs", "/docs/*"] {
+            let result = generate_mount_handler(&MountConfig {
+                path: String::from(path),
+                root: PathBuf::from("docs/build"),
+                ..MountConfig::default()
+            });
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "{path}"
+            );
+        }
+
+        let result = generate_mount_handler(&MountConfig {
+            path: String::from("/docs/"),
+            root: PathBuf::from("docs/build"),
+            errors: Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    503,
+                    ErrorConfig {
+                        file_path: PathBuf::from("error-503.html"),
+                        ..ErrorConfig::default()
+                    },
+                )]),
+            }),
+            ..MountConfig::default()
+        });
+        assert!(matches!(
+            result,
+            Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+        ));
+    }
+
+    #[test]
+    fn generates_site_routes() {
+        let site = SiteConfig {
+            hosts: vec!["docs.example.com".to_string()],
+            root: Some(PathBuf::from("sites/docs")),
+            errors: Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("404.html"),
+                        ..ErrorConfig::default()
+                    },
+                )]),
+            }),
+            headers: Some(vec![PathMatchedHeader {
+                path_matcher: String::from("*"),
+                key: String::from("X-Site"),
+                value: String::from("docs"),
+                ..PathMatchedHeader::default()
+            }]),
+            ..SiteConfig::default()
+        };
+        let (route, error_route) =
+            generate_site_routes(&site, SecurityHeadersPreset::Off, None, &[]).unwrap();
+
+        assert_eq!(
+            route,
+            json!({"handle":[{"handler":"subroute","routes":[{"handle":[{"handler":"headers","response":{"set":{"X-Site":["docs"]}}}],"match":[{"path":["*"]}]},{"handle":[{"encodings":{"gzip":{"level":6},"zstd":{"level":"default"}},"handler":"encode","prefer":["zstd","gzip"]},{"handler":"file_server","index_names":["index.html"],"pass_thru":true,"root":"sites/docs"}]},{"handle":[{"handler":"rewrite","uri":"404.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"sites/docs","status_code":"404"}]}]}],"match":[{"host":["docs.example.com"]}],"terminal":true})
+        );
+        assert_eq!(error_route["match"], json!([{"host":["docs.example.com"]}]));
+        assert_eq!(error_route["terminal"], json!(true));
+        assert_eq!(
+            error_route["handle"][0]["routes"][0]["match"][0]["expression"]["name"],
+            json!("error_401")
+        );
+    }
+
+    #[test]
+    fn generates_site_routes_behind_maintenance_and_auth() {
+        let heroku_config = HerokuWebServerConfig {
+            maintenance: Some(MaintenanceConfig::default()),
+            forward_auth: Some(ForwardAuthConfig {
+                url_env: Some("FORWARD_AUTH_URL".to_string()),
+                ..ForwardAuthConfig::default()
+            }),
+            caddy_server_opts: Some(CaddyServerOpts {
+                basic_auth: Some(CaddyBasicAuthConfig::Enabled(true)),
+                ..CaddyServerOpts::default()
+            }),
+            sites: Some(vec![SiteConfig {
+                hosts: vec!["docs.example.com".to_string()],
+                ..SiteConfig::default()
+            }]),
             ..HerokuWebServerConfig::default()
         };
+        let caddy_config =
+            caddy_json_config(&heroku_config, &DocRootFiles::default(), &[]).unwrap();
+        let site_route = caddy_config["apps"]["http"]["servers"]["public"]["routes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|route| route["match"] == json!([{"host":["docs.example.com"]}]))
+            .expect("should have the site route");
+        let site_routes = site_route["handle"][0]["routes"].as_array().unwrap();
+
+        assert!(site_routes.contains(
+            &generate_maintenance_route(
+                &MaintenanceConfig::default(),
+                DEFAULT_DOC_ROOT,
+                DEFAULT_DOC_INDEX,
+                &HtmlFilePolicies::new()
+            )
+            .unwrap()
+        ));
+        let handlers = site_routes
+            .iter()
+            .filter_map(|route| route["handle"].as_array())
+            .find(|handlers| handlers.iter().any(|h| h["handler"] == "file_server"))
+            .expect("should have the site's file server route");
+        assert_eq!(
+            handlers[0],
+            generate_basic_auth_handler(&CaddyBasicAuthConfig::Enabled(true), &[])
+        );
+        assert_eq!(
+            handlers[1],
+            generate_forward_auth_handler(heroku_config.forward_auth.as_ref().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn generates_site_routes_errors() {
+        let result = generate_site_routes(
+            &SiteConfig::default(),
+            SecurityHeadersPreset::Off,
+            None,
+            &[],
+        );
+        assert!(matches!(
+            result,
+            Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+        ));
+    }
+
+    #[test]
+    fn generates_canonical_host_routes() {
+        let routes = generate_canonical_host_routes(&CanonicalHostConfig {
+            host: Some("www.example.com".to_string()),
+            ..CanonicalHostConfig::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"match":[{"expression":{"expr":"{http.request.host} != 'www.example.com'","name":"canonical_host"}}],"handle":[{"handler":"static_response","status_code":301,"headers":{"Location":["https://www.example.com{http.request.uri}"]}}],"terminal":true})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_canonical_host_routes_from_env() {
+        let routes = generate_canonical_host_routes(&CanonicalHostConfig {
+            host_env: Some("CANONICAL_HOST".to_string()),
+            status: Some(308),
+            path_exclusions: Some(vec!["/health".to_string()]),
+            ..CanonicalHostConfig::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"match":[{"expression":{"expr":"{env.CANONICAL_HOST} != '' && {http.request.host} != {env.CANONICAL_HOST}","name":"canonical_host"},"not":[{"path":["/health"]}]}],"handle":[{"handler":"static_response","status_code":308,"headers":{"Location":["https://{env.CANONICAL_HOST}{http.request.uri}"]}}],"terminal":true})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_canonical_host_routes_errors() {
+        for canonical_host in [
+            CanonicalHostConfig::default(),
+            CanonicalHostConfig {
+                host: Some("www.example.com".to_string()),
+                host_env: Some("CANONICAL_HOST".to_string()),
+                ..CanonicalHostConfig::default()
+            },
+            CanonicalHostConfig {
+                host: Some("https://www.example.com/".to_string()),
+                ..CanonicalHostConfig::default()
+            },
+            CanonicalHostConfig {
+                host_env: Some("{env.CANONICAL_HOST}".to_string()),
+                ..CanonicalHostConfig::default()
+            },
+            CanonicalHostConfig {
+                host: Some("www.example.com".to_string()),
+                status: Some(302),
+                ..CanonicalHostConfig::default()
+            },
+        ] {
+            let result = generate_canonical_host_routes(&canonical_host);
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "{canonical_host:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn generates_access_routes() {
+        let routes = generate_access_routes(&AccessConfig {
+            allow: Some(vec![
+                "203.0.113.0/24".to_string(),
+                "2001:db8::1".to_string(),
+            ]),
+            allow_env: Some("ACCESS_ALLOW_IPS".to_string()),
+            deny: Some(vec!["203.0.113.13".to_string()]),
+            deny_env: None,
+        })
+        .unwrap();
+
+        assert_eq!(
+            routes,
+            vec![
+                json!({"match":[{"client_ip":{"ranges":["203.0.113.13"]}}],"handle":[{"handler":"error","status_code":403}],"terminal":true}),
+                json!({"match":[{"not":[{"client_ip":{"ranges":["203.0.113.0/24","2001:db8::1","{env.ACCESS_ALLOW_IPS}"]}}]}],"handle":[{"handler":"error","status_code":403}],"terminal":true})
+            ]
+        );
+    }
+
+    #[test]
+    fn generates_access_routes_errors() {
+        for access in [
+            AccessConfig {
+                allow: Some(vec!["office".to_string()]),
+                ..AccessConfig::default()
+            },
+            AccessConfig {
+                deny: Some(vec!["203.0.113.0/33".to_string()]),
+                ..AccessConfig::default()
+            },
+            AccessConfig {
+                allow_env: Some("{env.ACCESS_ALLOW_IPS}".to_string()),
+                ..AccessConfig::default()
+            },
+        ] {
+            let result = generate_access_routes(&access);
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "{access:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn trusts_router_for_access_client_ip() {
+        let caddy_config = caddy_json_config(
+            &HerokuWebServerConfig {
+                access: Some(AccessConfig {
+                    allow: Some(vec!["203.0.113.0/24".to_string()]),
+                    ..AccessConfig::default()
+                }),
+                ..HerokuWebServerConfig::default()
+            },
+            &DocRootFiles::default(),
+            &[],
+        )
+        .unwrap();
+        let public_server = &caddy_config["apps"]["http"]["servers"]["public"];
+
+        assert_eq!(
+            public_server["trusted_proxies"],
+            json!({"source":"static","ranges":["192.168.0.0/16","172.16.0.0/12","10.0.0.0/8","127.0.0.1/8","fd00::/8","::1"]})
+        );
+        assert_eq!(
+            public_server["client_ip_headers"],
+            json!(["X-Forwarded-For"])
+        );
+        assert_eq!(public_server["trusted_proxies_strict"], json!(1));
+    }
+
+    #[test]
+    fn generates_custom_trusted_proxies() {
+        let mut server = json!({});
+        generate_trusted_proxies(
+            Some(&CaddyTrustedProxiesConfig {
+                enabled: None,
+                ranges: Some(vec!["10.0.0.0/8".to_string(), "fd00::/8".to_string()]),
+                client_ip_headers: Some(vec!["X-Real-IP".to_string()]),
+            }),
+            &mut server,
+        )
+        .unwrap();
+        assert_eq!(
+            server,
+            json!({
+                "trusted_proxies": {"source":"static","ranges":["10.0.0.0/8","fd00::/8"]},
+                "client_ip_headers": ["X-Real-IP"],
+                "trusted_proxies_strict": 1
+            })
+        );
+
+        generate_trusted_proxies(
+            Some(&CaddyTrustedProxiesConfig {
+                ranges: Some(vec![
+                    "private_ranges".to_string(),
+                    "198.51.100.0/24".to_string(),
+                ]),
+                ..CaddyTrustedProxiesConfig::default()
+            }),
+            &mut server,
+        )
+        .unwrap();
+        assert_eq!(
+            server["trusted_proxies"],
+            json!({"source":"static","ranges":["192.168.0.0/16","172.16.0.0/12","10.0.0.0/8","127.0.0.1/8","fd00::/8","::1","198.51.100.0/24"]})
+        );
+
+        let result = generate_trusted_proxies(
+            Some(&CaddyTrustedProxiesConfig {
+                ranges: Some(vec!["router".to_string()]),
+                ..CaddyTrustedProxiesConfig::default()
+            }),
+            &mut server,
+        );
+        assert!(matches!(
+            result,
+            Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+        ));
+    }
+
+    #[test]
+    fn disables_trusted_proxies() {
+        let caddy_config = caddy_json_config(
+            &HerokuWebServerConfig {
+                caddy_server_opts: Some(CaddyServerOpts {
+                    trusted_proxies: Some(CaddyTrustedProxiesConfig {
+                        enabled: Some(false),
+                        ..CaddyTrustedProxiesConfig::default()
+                    }),
+                    ..CaddyServerOpts::default()
+                }),
+                ..HerokuWebServerConfig::default()
+            },
+            &DocRootFiles::default(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            caddy_config["apps"]["http"]["servers"]["public"].get("trusted_proxies"),
+            None
+        );
+    }
 
-        let routes = generate_response_headers_routes(&heroku_config.headers.unwrap());
+    #[test]
+    fn generates_cors_routes() {
+        let routes = generate_cors_routes(&[CorsConfig {
+            path_matcher: "/fonts/*".to_string(),
+            allowed_origins: Some(vec![
+                "https://app.example.com".to_string(),
+                "http://localhost:3000".to_string(),
+            ]),
+            allowed_origins_env: Some("CORS_ALLOWED_ORIGINS".to_string()),
+            allowed_headers: Some(vec!["Content-Type".to_string()]),
+            allow_credentials: Some(true),
+            max_age: Some(3600),
+            ..CorsConfig::default()
+        }])
+        .unwrap();
 
         assert_eq!(
             routes,
             vec![
-                json!({"handle":[{"handler":"headers","response":{"set":{"X-Foo":["Bar"],"X-Zuu":["Zem"]}}}],"match":[{"path":["*"]}]}),
-                json!({"handle":[{"handler":"headers","response":{"set":{"X-Baz":["Buz"]}}}],"match":[{"path":["*.html"]}]})
+                json!({"match":[{"path":["/fonts/*"]}],"handle":[{"handler":"subroute","routes":[
+                    {"handle":[{"handler":"headers","response":{"add":{"Vary":["Origin"]}}}]},
+                    {"match":[{"expression":{"expr":"{http.request.header.Origin} != '' && ({http.request.header.Origin} in ['https://app.example.com', 'http://localhost:3000'] || (',' + {env.CORS_ALLOWED_ORIGINS} + ',').contains(',' + {http.request.header.Origin} + ','))","name":"cors_origin_allowed"},"method":["OPTIONS"],"header":{"Access-Control-Request-Method":[]}}],"handle":[{"handler":"static_response","status_code":204,"headers":{"Access-Control-Allow-Origin":["{http.request.header.Origin}"],"Access-Control-Allow-Credentials":["true"],"Access-Control-Allow-Methods":["GET, HEAD"],"Access-Control-Allow-Headers":["Content-Type"],"Access-Control-Max-Age":["3600"]}}],"terminal":true},
+                    {"match":[{"expression":{"expr":"{http.request.header.Origin} != '' && ({http.request.header.Origin} in ['https://app.example.com', 'http://localhost:3000'] || (',' + {env.CORS_ALLOWED_ORIGINS} + ',').contains(',' + {http.request.header.Origin} + ','))","name":"cors_origin_allowed"}}],"handle":[{"handler":"headers","response":{"set":{"Access-Control-Allow-Origin":["{http.request.header.Origin}"],"Access-Control-Allow-Credentials":["true"]}}}]}
+                ]}]})
             ]
         );
     }
 
     #[test]
-    fn generates_global_response_headers_routes() {
-        let heroku_config = HerokuWebServerConfig {
-            headers: Some(vec![PathMatchedHeader {
-                path_matcher: String::from("*"),
-                key: String::from("X-Foo"),
-                value: String::from("Bar"),
-            }]),
-            ..HerokuWebServerConfig::default()
-        };
-
-        let routes = generate_response_headers_routes(&heroku_config.headers.unwrap());
+    fn generates_cors_routes_for_any_origin() {
+        let routes = generate_cors_routes(&[CorsConfig {
+            path_matcher: "*.json".to_string(),
+            allowed_origins: Some(vec!["*".to_string()]),
+            ..CorsConfig::default()
+        }])
+        .unwrap();
 
         assert_eq!(
             routes,
             vec![
-                json!({"handle": [{"handler":"headers","response":{"set":{"X-Foo":["Bar"]}}}],"match":[{"path":["*"]}]})
+                json!({"match":[{"path":["*.json"]}],"handle":[{"handler":"subroute","routes":[
+                    {"match":[{"expression":{"expr":"{http.request.header.Origin} != '' && (true)","name":"cors_origin_allowed"},"method":["OPTIONS"],"header":{"Access-Control-Request-Method":[]}}],"handle":[{"handler":"static_response","status_code":204,"headers":{"Access-Control-Allow-Origin":["*"],"Access-Control-Allow-Methods":["GET, HEAD"]}}],"terminal":true},
+                    {"match":[{"expression":{"expr":"{http.request.header.Origin} != '' && (true)","name":"cors_origin_allowed"}}],"handle":[{"handler":"headers","response":{"set":{"Access-Control-Allow-Origin":["*"]}}}]}
+                ]}]})
             ]
         );
     }
 
     #[test]
-    fn generates_custom_404_error_route() {
-        let doc_root = String::from("tests/fixtures/custom_errors/public");
-        let doc_index = "index.html".to_string();
-
-        let heroku_config = HerokuWebServerConfig {
-            errors: Some(ErrorsConfig {
-                custom_404_page: Some(ErrorConfig {
-                    file_path: PathBuf::from("error-404.html"),
-                    status: None,
-                    path_exclusions: None,
-                }),
-            }),
-            ..HerokuWebServerConfig::default()
+    fn generates_cors_routes_errors() {
+        let cors_config = CorsConfig {
+            path_matcher: "/fonts/*".to_string(),
+            allowed_origins: Some(vec!["https://app.example.com".to_string()]),
+            ..CorsConfig::default()
         };
+        for cors_config in [
+            CorsConfig {
+                allowed_origins: None,
+                ..cors_config.clone()
+            },
+            CorsConfig {
+                allowed_origins: Some(vec!["https://app.example.com/path".to_string()]),
+                ..cors_config.clone()
+            },
+            CorsConfig {
+                allowed_origins: Some(vec!["https://app.example.com' || true".to_string()]),
+                ..cors_config.clone()
+            },
+            CorsConfig {
+                allowed_origins_env: Some("{env.CORS_ALLOWED_ORIGINS}".to_string()),
+                ..cors_config.clone()
+            },
+            CorsConfig {
+                allowed_origins: Some(vec!["*".to_string()]),
+                allow_credentials: Some(true),
+                ..cors_config.clone()
+            },
+            CorsConfig {
+                allowed_methods: Some(vec!["GET, POST".to_string()]),
+                ..cors_config.clone()
+            },
+            CorsConfig {
+                allowed_headers: Some(vec!["X-Custom: 1".to_string()]),
+                ..cors_config.clone()
+            },
+        ] {
+            let result = generate_cors_routes(std::slice::from_ref(&cors_config));
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "{cors_config:?} should be invalid"
+            );
+        }
+    }
 
-        let routes = generate_error_404_route(&doc_root, &doc_index, heroku_config.errors.as_ref());
+    #[test]
+    fn generates_force_https_routes() {
+        let routes = generate_force_https_routes(&ForceHttpsConfig::default()).unwrap();
 
         assert_eq!(
             routes,
             vec![
-                json!({"handle":[{"handler":"rewrite","uri":"error-404.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/custom_errors/public","status_code":"404"}]})
+                json!({"match":[{"header":{"X-Forwarded-Proto":["http"]}}],"handle":[{"handler":"static_response","status_code":301,"headers":{"Location":["https://{http.request.host}{http.request.uri}"]}}],"terminal":true})
             ]
         );
     }
 
     #[test]
-    fn generates_custom_404_to_200_error_route() {
-        let doc_root = String::from("tests/fixtures/client_side_routing/public");
-        let doc_index = "index.html".to_string();
-
-        let heroku_config = HerokuWebServerConfig {
-            errors: Some(ErrorsConfig {
-                custom_404_page: Some(ErrorConfig {
-                    file_path: PathBuf::from("index.html"),
-                    status: Some(200),
-                    path_exclusions: None,
-                }),
+    fn generates_force_https_routes_with_exclusions_and_hsts() {
+        let routes = generate_force_https_routes(&ForceHttpsConfig {
+            status: Some(308),
+            path_exclusions: Some(vec!["/health".to_string()]),
+            hsts: Some(HstsConfig {
+                max_age: None,
+                include_subdomains: Some(true),
+                preload: Some(true),
             }),
-            ..HerokuWebServerConfig::default()
-        };
-
-        let routes = generate_error_404_route(&doc_root, &doc_index, heroku_config.errors.as_ref());
+        })
+        .unwrap();
 
         assert_eq!(
             routes,
             vec![
-                json!({"handle":[{"handler":"rewrite","uri":"index.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/client_side_routing/public","status_code":"200"}]})
+                json!({"match":[{"header":{"X-Forwarded-Proto":["http"]},"not":[{"path":["/health"]}]}],"handle":[{"handler":"static_response","status_code":308,"headers":{"Location":["https://{http.request.host}{http.request.uri}"]}}],"terminal":true}),
+                json!({"match":[{"header":{"X-Forwarded-Proto":["https"]}}],"handle":[{"handler":"headers","response":{"set":{"Strict-Transport-Security":["max-age=31536000; includeSubDomains; preload"]}}}]})
             ]
         );
     }
 
     #[test]
-    fn generates_custom_404_error_route_with_path_exclusions() {
-        let doc_root = String::from("tests/fixtures/client_side_routing/public");
-        let doc_index = "index.html".to_string();
-
-        let heroku_config = HerokuWebServerConfig {
-            errors: Some(ErrorsConfig {
-                custom_404_page: Some(ErrorConfig {
-                    file_path: PathBuf::from("index.html"),
-                    status: Some(200),
-                    path_exclusions: Some(vec!["/assets/*".to_string()]),
-                }),
-            }),
-            ..HerokuWebServerConfig::default()
-        };
-
-        let routes = generate_error_404_route(&doc_root, &doc_index, heroku_config.errors.as_ref());
+    fn generates_force_https_routes_errors() {
+        let result = generate_force_https_routes(&ForceHttpsConfig {
+            status: Some(302),
+            ..ForceHttpsConfig::default()
+        });
+        assert!(matches!(
+            result,
+            Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+        ));
+    }
 
+    #[test]
+    fn generates_trailing_slash_always_routes() {
+        let routes = generate_trailing_slash_routes(TrailingSlashMode::Always, "public", true);
         assert_eq!(
             routes,
             vec![
-                json!({"match":[{"not":[{"path":["/assets/*"]}]}],"handle":[{"handler":"rewrite","uri":"index.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/client_side_routing/public","status_code":"200"}]}),
-                json!({"handle":[{"body":"\n    <!DOCTYPE html>\n    <html lang=\"en\">\n    <head>\n        <meta charset=\"utf-8\">\n        <title>404 Not Found</title>\n    </head>\n    <body>\n        <h1>404 Not Found</h1>\n    </body>\n    </html>\n","handler":"static_response","headers":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Content-Type":["text/html"],"Pragma":["no-cache"]},"status_code":"404"}]})
+                json!({"match":[{"not":[{"path":["*/"]},{"path_regexp":{"pattern":"^//"}}],"file":{"root":"public","try_files":["{http.request.uri.path}/","{http.request.uri.path}.html"]}}],"handle":[{"handler":"subroute","routes":[{"match":[{"expression":{"expr":"{http.request.uri.query} != ''","name":"redirect_query_present"}}],"handle":[{"handler":"static_response","status_code":301,"headers":{"Location":["{http.request.uri.path}/?{http.request.uri.query}"]}}]},{"handle":[{"handler":"static_response","status_code":301,"headers":{"Location":["{http.request.uri.path}/"]}}]}]}],"terminal":true})
             ]
         );
-    }
-
-    #[test]
-    fn generates_custom_404_error_route_with_multiple_path_exclusions() {
-        let doc_root = String::from("tests/fixtures/client_side_routing/public");
-        let doc_index = "index.html".to_string();
 
-        let heroku_config = HerokuWebServerConfig {
-            errors: Some(ErrorsConfig {
-                custom_404_page: Some(ErrorConfig {
-                    file_path: PathBuf::from("index.html"),
-                    status: Some(200),
-                    path_exclusions: Some(vec!["/assets/*".to_string(), "/static/*".to_string()]),
-                }),
-            }),
-            ..HerokuWebServerConfig::default()
-        };
+        let mut handlers = vec![];
+        generate_trailing_slash_handlers(TrailingSlashMode::Always, "public", true, &mut handlers);
+        assert_eq!(
+            handlers,
+            vec![
+                json!({"handler":"subroute","routes":[{"match":[{"path_regexp":{"name":"trailing_slash_html","pattern":"^(.+)/$"}}],"handle":[{"handler":"subroute","routes":[{"match":[{"file":{"root":"public","try_files":["{http.regexp.trailing_slash_html.1}.html"]}}],"handle":[{"handler":"rewrite","uri":"{http.matchers.file.relative}"}]}]}]}]})
+            ]
+        );
 
-        let routes = generate_error_404_route(&doc_root, &doc_index, heroku_config.errors.as_ref());
+        let mut handlers = vec![];
+        generate_trailing_slash_handlers(TrailingSlashMode::Always, "public", false, &mut handlers);
+        assert!(handlers.is_empty());
+    }
 
+    #[test]
+    fn generates_trailing_slash_never_routes() {
+        let routes = generate_trailing_slash_routes(TrailingSlashMode::Never, "public", false);
         assert_eq!(
             routes,
             vec![
-                json!({"match":[{"not":[{"path":["/assets/*","/static/*"]}]}],"handle":[{"handler":"rewrite","uri":"index.html"},{"handler":"headers","response":{"set":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Pragma":["no-cache"]}}},{"handler":"file_server","index_names":["index.html"],"pass_thru":false,"root":"tests/fixtures/client_side_routing/public","status_code":"200"}]}),
-                json!({"handle":[{"body":"\n    <!DOCTYPE html>\n    <html lang=\"en\">\n    <head>\n        <meta charset=\"utf-8\">\n        <title>404 Not Found</title>\n    </head>\n    <body>\n        <h1>404 Not Found</h1>\n    </body>\n    </html>\n","handler":"static_response","headers":{"Cache-Control":["no-store, no-cache, must-revalidate"],"Content-Type":["text/html"],"Pragma":["no-cache"]},"status_code":"404"}]})
+                json!({"match":[{"path_regexp":{"name":"trailing_slash","pattern":"^(.+)/$"},"not":[{"path_regexp":{"pattern":"^//"}}]}],"handle":[{"handler":"subroute","routes":[{"match":[{"file":{"root":"public","try_files":["{http.regexp.trailing_slash.1}/"]}}],"handle":[{"handler":"subroute","routes":[{"match":[{"expression":{"expr":"{http.request.uri.query} != ''","name":"redirect_query_present"}}],"handle":[{"handler":"static_response","status_code":301,"headers":{"Location":["{http.regexp.trailing_slash.1}?{http.request.uri.query}"]}}]},{"handle":[{"handler":"static_response","status_code":301,"headers":{"Location":["{http.regexp.trailing_slash.1}"]}}]}]}],"terminal":true}]}]})
+            ]
+        );
+
+        let mut handlers = vec![];
+        generate_trailing_slash_handlers(TrailingSlashMode::Never, "public", false, &mut handlers);
+        assert_eq!(
+            handlers,
+            vec![
+                json!({"handler":"subroute","routes":[{"match":[{"not":[{"path":["*/"]}],"file":{"root":"public","try_files":["{http.request.uri.path}/"]}}],"handle":[{"handler":"rewrite","uri":"{http.matchers.file.relative}"}]}]})
             ]
         );
     }
 
     #[test]
-    fn generates_static_response_handlers() {
-        let heroku_config = HerokuWebServerConfig {
-            caddy_server_opts: Some(CaddyServerOpts {
-                static_responses: Some(vec![
-                    CaddyStaticResponseConfig {
-                        host_matcher: Some("original.example.com".to_string()),
-                        path_matcher: None,
-                        status: Some(301),
-                        headers: Some(vec![
-                            Header {
-                                key: "Location".to_string(),
-                                value: "https://new.example.com{http.request.uri}".to_string(),
-                            },
-                            Header {
-                                key: "X-Redirected-From".to_string(),
-                                value: "original.example.com".to_string(),
-                            },
-                        ]),
-                        body: None,
-                    },
-                    CaddyStaticResponseConfig {
-                        host_matcher: Some("original.example.com".to_string()),
-                        path_matcher: Some("/blog/*".to_string()),
-                        status: Some(301),
-                        headers: Some(vec![Header {
-                            key: "Location".to_string(),
-                            value:
-                                "https://{http.request.host}/new-blog/{http.request.uri.path.file}"
-                                    .to_string(),
-                        }]),
-                        body: None,
-                    },
-                    CaddyStaticResponseConfig {
-                        host_matcher: None,
-                        path_matcher: Some("/api/*".to_string()),
-                        status: Some(500),
-                        headers: Some(vec![Header {
-                            key: "Content-Type".to_string(),
-                            value: "application/json".to_string(),
-                        }]),
-                        body: Some(r#"{"error":"Service not available"}"#.to_string()),
-                    },
-                ]),
-                ..CaddyServerOpts::default()
-            }),
-            ..HerokuWebServerConfig::default()
-        };
+    fn generates_trailing_slash_routes_without_protocol_relative_redirects() {
+        let pattern = protocol_relative_path_matcher()["path_regexp"]["pattern"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let pattern = Regex::new(&pattern).unwrap();
+        assert!(pattern.is_match("//docs/"));
+        assert!(pattern.is_match("//evil.example"));
+        assert!(!pattern.is_match("/docs/"));
+        assert!(!pattern.is_match("/docs//"));
+
+        let routes = generate_trailing_slash_routes(TrailingSlashMode::Always, "public", false);
+        assert_eq!(
+            routes[0]["match"][0]["not"][1],
+            protocol_relative_path_matcher()
+        );
+        let routes = generate_trailing_slash_routes(TrailingSlashMode::Never, "public", false);
+        assert_eq!(
+            routes[0]["match"][0]["not"][0],
+            protocol_relative_path_matcher()
+        );
+    }
+
+    #[test]
+    fn generates_trailing_slash_ignore_routes() {
+        assert!(
+            generate_trailing_slash_routes(TrailingSlashMode::Ignore, "public", true).is_empty()
+        );
 
         let mut handlers = vec![];
-        generate_static_response_handlers(&heroku_config, &mut handlers).unwrap();
+        generate_trailing_slash_handlers(TrailingSlashMode::Ignore, "public", true, &mut handlers);
+        assert!(handlers.is_empty());
+    }
 
-        assert_eq!(handlers.len(), 3);
+    #[test]
+    fn generates_redirect_routes() {
+        let redirects = vec![
+            RedirectConfig {
+                from: Some("/about-us".to_string()),
+                from_regex: None,
+                to: "/about".to_string(),
+                status: None,
+                preserve_query: None,
+            },
+            RedirectConfig {
+                from: Some("/blog/*/*.html".to_string()),
+                from_regex: None,
+                to: "/posts/{1}/{2}".to_string(),
+                status: Some(308),
+                preserve_query: None,
+            },
+            RedirectConfig {
+                from: None,
+                from_regex: Some(r"^/docs/v(\d+)/(.*)$".to_string()),
+                to: "https://docs.example.com/{2}?version={1}".to_string(),
+                status: Some(302),
+                preserve_query: Some(true),
+            },
+        ];
+
+        let routes = generate_redirect_routes(&redirects).unwrap();
 
         assert_eq!(
-            handlers[0],
-            json!({
-                "handler": "subroute",
-                "routes": [{
-                    "match": [{"host": ["original.example.com"]}],
+            routes,
+            vec![
+                json!({
+                    "match": [{"path": ["/about-us"]}],
                     "handle": [{
                         "handler": "static_response",
                         "status_code": 301,
-                        "headers": {
-                            "Location": ["https://new.example.com{http.request.uri}"],
-                            "X-Redirected-From": ["original.example.com"]
-                        },
-                        "body": null
+                        "headers": {"Location": ["/about"]}
                     }],
                     "terminal": true
-                }]
-            })
-        );
-
-        assert_eq!(
-            handlers[1],
-            json!({
-                "handler": "subroute",
-                "routes": [{
-                    "match": [{"host": ["original.example.com"]}, {"path": ["/blog/*"]}],
+                }),
+                json!({
+                    "match": [{"path_regexp": {"name": "redirect_1", "pattern": r"^/blog/(.*)/(.*)\.html$"}}],
                     "handle": [{
                         "handler": "static_response",
-                        "status_code": 301,
-                        "headers": {"Location": ["https://{http.request.host}/new-blog/{http.request.uri.path.file}"]},
-                        "body": null
+                        "status_code": 308,
+                        "headers": {"Location": ["/posts/{http.regexp.redirect_1.1}/{http.regexp.redirect_1.2}"]}
                     }],
                     "terminal": true
-                }]
-            })
+                }),
+                json!({
+                    "match": [{"path_regexp": {"name": "redirect_2", "pattern": r"^/docs/v(\d+)/(.*)$"}}],
+                    "handle": [{
+                        "handler": "subroute",
+                        "routes": [
+                            {
+                                "match": [{
+                                    "expression": {
+                                        "expr": "{http.request.uri.query} != ''",
+                                        "name": "redirect_query_present"
+                                    }
+                                }],
+                                "handle": [{
+                                    "handler": "static_response",
+                                    "status_code": 302,
+                                    "headers": {"Location": ["https://docs.example.com/{http.regexp.redirect_2.2}?version={http.regexp.redirect_2.1}&{http.request.uri.query}"]}
+                                }]
+                            },
+                            {
+                                "handle": [{
+                                    "handler": "static_response",
+                                    "status_code": 302,
+                                    "headers": {"Location": ["https://docs.example.com/{http.regexp.redirect_2.2}?version={http.regexp.redirect_2.1}"]}
+                                }]
+                            }
+                        ]
+                    }],
+                    "terminal": true
+                }),
+            ]
         );
+    }
+
+    #[test]
+    fn generates_redirect_routes_errors() {
+        let invalid_redirects = [
+            RedirectConfig {
+                from: Some("/old".to_string()),
+                to: "/new".to_string(),
+                status: Some(200),
+                ..RedirectConfig::default()
+            },
+            RedirectConfig {
+                to: "/new".to_string(),
+                ..RedirectConfig::default()
+            },
+            RedirectConfig {
+                from: Some("/old".to_string()),
+                from_regex: Some("^/old$".to_string()),
+                to: "/new".to_string(),
+                ..RedirectConfig::default()
+            },
+            RedirectConfig {
+                from: Some("/old/*".to_string()),
+                to: "/new/{2}".to_string(),
+                ..RedirectConfig::default()
+            },
+            RedirectConfig {
+                from_regex: Some("^/old/(.*$".to_string()),
+                to: "/new/{1}".to_string(),
+                ..RedirectConfig::default()
+            },
+        ];
+
+        for redirect in invalid_redirects {
+            let result = generate_redirect_routes(std::slice::from_ref(&redirect));
+            assert!(
+                matches!(
+                    result,
+                    Err(StaticWebServerBuildpackError::ConfigurationConstraint(_))
+                ),
+                "should be a configuration constraint error: {redirect:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn generates_rewrite_handlers() {
+        let heroku_config = HerokuWebServerConfig {
+            rewrites: Some(vec![
+                RewriteConfig {
+                    from: Some("/blog/*".to_string()),
+                    from_regex: None,
+                    to: "/blog/post.html".to_string(),
+                    if_target_exists: None,
+                },
+                RewriteConfig {
+                    from: None,
+                    from_regex: Some("^/app/([a-z]+)/.*$".to_string()),
+                    to: "/app/{1}.html".to_string(),
+                    if_target_exists: Some(true),
+                },
+                RewriteConfig {
+                    from: Some("/app/*".to_string()),
+                    from_regex: None,
+                    to: "/app/index.html".to_string(),
+                    if_target_exists: None,
+                },
+            ]),
+            ..HerokuWebServerConfig::default()
+        };
+
+        let mut handlers = vec![];
+        generate_rewrite_handlers(&heroku_config, "public", &mut handlers).unwrap();
 
         assert_eq!(
-            handlers[2],
-            json!({
+            handlers,
+            vec![json!({
                 "handler": "subroute",
-                "routes": [{
-                    "match": [{"path": ["/api/*"]}],
-                    "handle": [{
-                        "handler": "static_response",
-                        "status_code": 500,
-                        "headers": {"Content-Type": ["application/json"]},
-                        "body": r#"{"error":"Service not available"}"#
-                    }],
-                    "terminal": true
-                }]
-            })
+                "routes": [
+                    {
+                        "group": "rewrites",
+                        "match": [{"path_regexp": {"name": "rewrite_0", "pattern": "^/blog/(.*)$"}}],
+                        "handle": [{"handler": "rewrite", "uri": "/blog/post.html"}]
+                    },
+                    {
+                        "match": [{"path_regexp": {"name": "rewrite_1", "pattern": "^/app/([a-z]+)/.*$"}}],
+                        "handle": [{
+                            "handler": "subroute",
+                            "routes": [{
+                                "group": "rewrites",
+                                "match": [{"file": {"root": "public", "try_files": ["/app/{http.regexp.rewrite_1.1}.html"]}}],
+                                "handle": [{"handler": "rewrite", "uri": "/app/{http.regexp.rewrite_1.1}.html"}]
+                            }]
+                        }]
+                    },
+                    {
+                        "group": "rewrites",
+                        "match": [{"path_regexp": {"name": "rewrite_2", "pattern": "^/app/(.*)$"}}],
+                        "handle": [{"handler": "rewrite", "uri": "/app/index.html"}]
+                    }
+                ]
+            })]
         );
     }
 
     #[test]
-    fn generates_static_response_handlers_error_when_no_matchers() {
+    fn generates_rewrite_handlers_error_when_no_from() {
         let heroku_config = HerokuWebServerConfig {
-            caddy_server_opts: Some(CaddyServerOpts {
-                static_responses: Some(vec![CaddyStaticResponseConfig {
-                    host_matcher: None,
-                    path_matcher: None,
-                    status: Some(200),
-                    headers: None,
-                    body: Some("test".to_string()),
-                }]),
-                ..CaddyServerOpts::default()
-            }),
+            rewrites: Some(vec![RewriteConfig {
+                to: "/index.html".to_string(),
+                ..RewriteConfig::default()
+            }]),
             ..HerokuWebServerConfig::default()
         };
 
         let mut handlers = vec![];
-        let result = generate_static_response_handlers(&heroku_config, &mut handlers);
+        let result = generate_rewrite_handlers(&heroku_config, "public", &mut handlers);
 
-        assert!(result.is_err());
         if let Err(StaticWebServerBuildpackError::ConfigurationConstraint(msg)) = result {
-            assert_eq!(
-                msg,
-                "host_matcher or path_matcher must be set for caddy_server_opts.static_responses"
-            );
+            assert_eq!(msg, "either from or from_regex must be set for rewrites");
         } else {
             panic!("Expected ConfigurationConstraint error");
         }
@@ -1,8 +1,15 @@
-use crate::caddy_config::caddy_json_config;
+use crate::basic_auth_users::{read_basic_auth_users_file, BasicAuthAccount};
+use crate::caddy_config::{caddy_json_config, proxy_upstream_env};
+use crate::content_etags::write_etag_files;
+use crate::csp_nonces::write_nonce_placeholders;
+use crate::doc_root_files::{find_fingerprinted_paths, list_doc_root_files, DocRootFiles};
 use crate::heroku_web_server_config::{
-    HerokuWebServerConfig, RuntimeConfig, DEFAULT_DOC_INDEX, DEFAULT_DOC_ROOT,
+    CachingMode, CaddyBasicAuthConfig, Executable, HerokuWebServerConfig, RuntimeConfig,
+    DEFAULT_DOC_INDEX, DEFAULT_DOC_ROOT,
 };
+use crate::inline_hashes::find_inline_hashes;
 use crate::o11y::*;
+use crate::precompress::precompress_files;
 use crate::{StaticWebServerBuildpack, StaticWebServerBuildpackError, BUILD_PLAN_ID};
 use glob::glob;
 use libcnb::additional_buildpack_binary_path;
@@ -55,30 +62,9 @@ pub(crate) fn config_web_server(
         "config web server"
     );
 
-    // Transform web server config to Caddy native JSON config
-    let caddy_config_json = serde_json::to_string(&caddy_json_config(&heroku_config)?)
-        .map_err(StaticWebServerBuildpackError::Json)?;
-
-    let config_path = configuration_layer.path().join("caddy.json");
-    fs::write(config_path, caddy_config_json)
-        .map_err(StaticWebServerBuildpackError::CannotWriteCaddyConfiguration)?;
-
     // Execute the optional build command
     if let Some(build_command) = build_command_opt {
-        log_info(format!("Executing build command: {build_command:#?}"));
-        tracing::info!(
-            { CONFIG_BUILD_COMMAND } = build_command.command,
-            "build command"
-        );
-        let mut cmd = Command::new(build_command.command);
-        if let Some(args) = build_command.args {
-            cmd.args(args);
-        }
-
-        cmd.stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .output()
-            .map_err(StaticWebServerBuildpackError::BuildCommandFailed)?;
+        execute_build_command(build_command)?;
     }
 
     // Set-up runtime configuration; defaults to enabled
@@ -91,33 +77,71 @@ pub(crate) fn config_web_server(
         { CONFIG_RUNTIME_CONFIG_ENABLED } = runtime_config_enabled,
         "runtime configuration"
     );
+
+    // Runtime configuration covers the HTML files of the document root & of each mount
+    let runtime_config_doc_roots =
+        runtime_config_doc_roots(&heroku_config, &doc_root_path, &doc_index);
+
+    // Files rewritten at launch by runtime configuration cannot be processed at build time
+    let runtime_config_files: Vec<PathBuf> = if runtime_config_enabled {
+        runtime_config_doc_roots
+            .iter()
+            .flat_map(|(root_path, index)| {
+                runtime_config_target_files(&context.app_dir, root_path, index, &runtime_config)
+            })
+            .map(|f| context.app_dir.join(f))
+            .collect()
+    } else {
+        vec![]
+    };
+
+    let doc_root = context.app_dir.join(&doc_root_path);
+    process_doc_root_files(&heroku_config, &doc_root, runtime_config_files)?;
+
+    // Inspect the files to be served, now that the optional build command has output them
+    let doc_root_files = inspect_doc_root_files(&heroku_config, &doc_root)?;
+
+    let basic_auth_accounts =
+        read_basic_auth_accounts(&heroku_config, &context.app_dir, &doc_root_path)?;
+
+    // Transform web server config to Caddy native JSON config
+    let caddy_config_json = serde_json::to_string(&caddy_json_config(
+        &heroku_config,
+        &doc_root_files,
+        &basic_auth_accounts,
+    )?)
+    .map_err(StaticWebServerBuildpackError::Json)?;
+
+    let config_path = configuration_layer.path().join("caddy.json");
+    fs::write(&config_path, caddy_config_json)
+        .map_err(StaticWebServerBuildpackError::CannotWriteCaddyConfiguration)?;
+
+    // Access ranges, Basic Auth accounts & the forward auth URL from env vars are expanded into
+    // the Caddy config at launch
+    if requires_caddy_config_env(&heroku_config) {
+        install_caddy_config_env(&configuration_layer, &config_path)?;
+    }
+    if is_basic_auth_enabled(&heroku_config) {
+        install_basic_auth_password_bcrypt(&configuration_layer)?;
+    }
+
     if runtime_config_enabled {
-        log_info("Installing runtime configuration process…");
-        let web_exec_destination = configuration_layer.path().join("exec.d/web");
-        let exec_path = web_exec_destination.join("env-as-html-data");
-        log_info(format!("  {}", exec_path.display()));
-        fs::create_dir_all(&web_exec_destination)
-            .map_err(StaticWebServerBuildpackError::CannotCreateWebExecD)?;
-        fs::copy(
-            additional_buildpack_binary_path!("env-as-html-data"),
-            exec_path,
-        )
-        .map_err(StaticWebServerBuildpackError::CannotInstallEnvAsHtmlData)?;
-
-        // Set env-to-html-data param as env variable
-        let mut configuration_layer_env = configuration_layer.read_env()?;
-        configuration_layer_env.insert(
-            Scope::Process("web".to_string()),
-            ModificationBehavior::Override,
-            "ENV_AS_HTML_DATA_TARGET_FILES",
-            list_runtime_config_target_files(
-                &context.app_dir,
-                &doc_root_path,
-                &doc_index,
-                &runtime_config,
-            ),
-        );
-        configuration_layer.write_env(configuration_layer_env)?;
+        install_env_as_html_data(
+            &configuration_layer,
+            runtime_config_doc_roots
+                .iter()
+                .map(|(root_path, index)| {
+                    list_runtime_config_target_files(
+                        &context.app_dir,
+                        root_path,
+                        index,
+                        &runtime_config,
+                    )
+                })
+                .filter(|files| !files.is_empty())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )?;
     } else {
         log_info("Runtime configuration is not enabled.");
     }
@@ -125,6 +149,268 @@ pub(crate) fn config_web_server(
     Ok(configuration_layer)
 }
 
+fn install_env_as_html_data(
+    configuration_layer: &LayerRef<StaticWebServerBuildpack, (), ()>,
+    target_files: String,
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    log_info("Installing runtime configuration process…");
+    let web_exec_destination = configuration_layer.path().join("exec.d/web");
+    let exec_path = web_exec_destination.join("env-as-html-data");
+    log_info(format!("  {}", exec_path.display()));
+    fs::create_dir_all(&web_exec_destination)
+        .map_err(StaticWebServerBuildpackError::CannotCreateWebExecD)?;
+    fs::copy(
+        additional_buildpack_binary_path!("env-as-html-data"),
+        exec_path,
+    )
+    .map_err(StaticWebServerBuildpackError::CannotInstallEnvAsHtmlData)?;
+
+    // Set env-to-html-data param as env variable
+    let mut configuration_layer_env = configuration_layer.read_env()?;
+    configuration_layer_env.insert(
+        Scope::Process("web".to_string()),
+        ModificationBehavior::Override,
+        "ENV_AS_HTML_DATA_TARGET_FILES",
+        target_files,
+    );
+    configuration_layer.write_env(configuration_layer_env)?;
+    Ok(())
+}
+
+fn requires_caddy_config_env(heroku_config: &HerokuWebServerConfig) -> bool {
+    let access_ranges_from_env = heroku_config
+        .access
+        .as_ref()
+        .is_some_and(|v| v.allow_env.is_some() || v.deny_env.is_some());
+    let proxy_upstreams_from_env = heroku_config
+        .proxies
+        .iter()
+        .flatten()
+        .any(|v| proxy_upstream_env(&v.upstream).is_some());
+    access_ranges_from_env
+        || proxy_upstreams_from_env
+        || is_basic_auth_enabled(heroku_config)
+        || heroku_config.forward_auth.is_some()
+}
+
+fn is_basic_auth_enabled(heroku_config: &HerokuWebServerConfig) -> bool {
+    heroku_config
+        .caddy_server_opts
+        .as_ref()
+        .and_then(|v| v.basic_auth.as_ref())
+        .is_some_and(CaddyBasicAuthConfig::is_enabled)
+}
+
+// The users file must not be served, so it must be outside the document root.
+fn read_basic_auth_accounts(
+    heroku_config: &HerokuWebServerConfig,
+    app_dir: &Path,
+    doc_root_path: &Path,
+) -> Result<Vec<BasicAuthAccount>, libcnb::Error<StaticWebServerBuildpackError>> {
+    let Some(users_file) = heroku_config
+        .caddy_server_opts
+        .as_ref()
+        .and_then(|v| v.basic_auth.as_ref())
+        .filter(|v| v.is_enabled())
+        .and_then(|v| v.opts())
+        .and_then(|v| v.users_file.as_ref())
+    else {
+        return Ok(vec![]);
+    };
+    let users_file_path = app_dir.join(users_file);
+    let is_in_doc_root = match (
+        users_file_path.canonicalize(),
+        app_dir.join(doc_root_path).canonicalize(),
+    ) {
+        (Ok(file), Ok(doc_root)) => file.starts_with(doc_root),
+        _ => false,
+    };
+    if is_in_doc_root {
+        return Err(StaticWebServerBuildpackError::ConfigurationConstraint(format!(
+            "caddy_server_opts.basic_auth.users_file must be outside the document root, so that it is not served, but got {}",
+            users_file.display()
+        ))
+        .into());
+    }
+    let accounts = read_basic_auth_users_file(&users_file_path)
+        .map_err(StaticWebServerBuildpackError::CannotReadBasicAuthUsersFile)?;
+    log_info(format!(
+        "Read {} Basic Auth accounts from {}",
+        accounts.len(),
+        users_file.display()
+    ));
+    Ok(accounts)
+}
+
+fn install_caddy_config_env(
+    configuration_layer: &LayerRef<StaticWebServerBuildpack, (), ()>,
+    config_path: &Path,
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    log_info("Installing Caddy config from env process…");
+    let web_exec_destination = configuration_layer.path().join("exec.d/web");
+    let exec_path = web_exec_destination.join("caddy-config-env");
+    log_info(format!("  {}", exec_path.display()));
+    fs::create_dir_all(&web_exec_destination)
+        .map_err(StaticWebServerBuildpackError::CannotCreateWebExecD)?;
+    fs::copy(
+        additional_buildpack_binary_path!("caddy-config-env"),
+        exec_path,
+    )
+    .map_err(StaticWebServerBuildpackError::CannotInstallCaddyConfigEnv)?;
+
+    let mut configuration_layer_env = configuration_layer.read_env()?;
+    configuration_layer_env.insert(
+        Scope::Process("web".to_string()),
+        ModificationBehavior::Override,
+        "CADDY_CONFIG_ENV_FILE",
+        config_path,
+    );
+    configuration_layer.write_env(configuration_layer_env)?;
+    Ok(())
+}
+
+// A plaintext WEB_BASIC_AUTH_PASSWORD is hashed at launch, so that it is never written to disk.
+fn install_basic_auth_password_bcrypt(
+    configuration_layer: &LayerRef<StaticWebServerBuildpack, (), ()>,
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    log_info("Installing Basic Auth password hashing process…");
+    let web_exec_destination = configuration_layer.path().join("exec.d/web");
+    let exec_path = web_exec_destination.join("basic-auth-password-bcrypt");
+    log_info(format!("  {}", exec_path.display()));
+    fs::create_dir_all(&web_exec_destination)
+        .map_err(StaticWebServerBuildpackError::CannotCreateWebExecD)?;
+    fs::copy(
+        additional_buildpack_binary_path!("basic-auth-password-bcrypt"),
+        exec_path,
+    )
+    .map_err(StaticWebServerBuildpackError::CannotInstallBasicAuthPasswordBcrypt)?;
+    Ok(())
+}
+
+// Rewrites the files to be served, as configured, before they are inspected.
+fn process_doc_root_files(
+    heroku_config: &HerokuWebServerConfig,
+    doc_root: &Path,
+    mut dynamic_files: Vec<PathBuf>,
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    // Files rendered per-request, with a Content-Security-Policy nonce, are dynamic too
+    if heroku_config
+        .content_security_policy
+        .as_ref()
+        .is_some_and(|v| v.nonce.is_some_and(|vv| vv))
+    {
+        dynamic_files.extend(write_doc_root_nonce_placeholders(doc_root)?);
+    }
+
+    if heroku_config.precompress.unwrap_or(false) {
+        precompress_doc_root_files(doc_root, &dynamic_files)?;
+    }
+
+    // ETags come after precompression, so that compressed siblings get their own
+    if heroku_config.content_etags.unwrap_or(false) {
+        write_doc_root_etag_files(doc_root, &dynamic_files)?;
+    }
+    Ok(())
+}
+
+fn inspect_doc_root_files(
+    heroku_config: &HerokuWebServerConfig,
+    doc_root: &Path,
+) -> Result<DocRootFiles, libcnb::Error<StaticWebServerBuildpackError>> {
+    let mut doc_root_files = DocRootFiles::default();
+    if heroku_config.caching == Some(CachingMode::Auto) {
+        doc_root_files.fingerprinted_paths = find_fingerprinted_paths(doc_root);
+        log_info(format!(
+            "Caching {} fingerprinted files long-term",
+            doc_root_files.fingerprinted_paths.len()
+        ));
+    }
+    if heroku_config.content_security_policy.is_some() {
+        doc_root_files.inline_hashes = find_inline_hashes(doc_root)
+            .map_err(StaticWebServerBuildpackError::CannotHashInlineContent)?;
+        log_info(format!(
+            "Hashed inline scripts & styles of {} HTML files for Content-Security-Policy",
+            doc_root_files.inline_hashes.len()
+        ));
+    }
+    Ok(doc_root_files)
+}
+
+fn execute_build_command(
+    build_command: Executable,
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    log_info(format!("Executing build command: {build_command:#?}"));
+    tracing::info!(
+        { CONFIG_BUILD_COMMAND } = build_command.command,
+        "build command"
+    );
+    let mut cmd = Command::new(build_command.command);
+    if let Some(args) = build_command.args {
+        cmd.args(args);
+    }
+
+    cmd.stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(StaticWebServerBuildpackError::BuildCommandFailed)?;
+    Ok(())
+}
+
+fn write_doc_root_nonce_placeholders(
+    doc_root: &Path,
+) -> Result<Vec<PathBuf>, libcnb::Error<StaticWebServerBuildpackError>> {
+    let files = list_doc_root_files_excluding(doc_root, &[]);
+    let rewritten_files = write_nonce_placeholders(&files)
+        .map_err(StaticWebServerBuildpackError::CannotWriteNoncePlaceholders)?;
+    log_info(format!(
+        "Wrote Content-Security-Policy nonce placeholders into {} HTML files",
+        rewritten_files.len()
+    ));
+    tracing::info!(
+        { CONFIG_CONTENT_SECURITY_POLICY_NONCE_FILES_COUNT } = rewritten_files.len(),
+        "content security policy nonce"
+    );
+    Ok(rewritten_files)
+}
+
+fn precompress_doc_root_files(
+    doc_root: &Path,
+    excluded_paths: &[PathBuf],
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    let files = list_doc_root_files_excluding(doc_root, excluded_paths);
+    let precompressed_count =
+        precompress_files(&files).map_err(StaticWebServerBuildpackError::CannotPrecompressFile)?;
+    log_info(format!("Precompressed {precompressed_count} files"));
+    tracing::info!(
+        { CONFIG_PRECOMPRESS_FILES_COUNT } = precompressed_count,
+        "precompress"
+    );
+    Ok(())
+}
+
+fn write_doc_root_etag_files(
+    doc_root: &Path,
+    excluded_paths: &[PathBuf],
+) -> Result<(), libcnb::Error<StaticWebServerBuildpackError>> {
+    let files = list_doc_root_files_excluding(doc_root, excluded_paths);
+    let etag_files_count =
+        write_etag_files(&files).map_err(StaticWebServerBuildpackError::CannotWriteEtagFile)?;
+    log_info(format!("Wrote content ETags for {etag_files_count} files"));
+    tracing::info!(
+        { CONFIG_CONTENT_ETAGS_FILES_COUNT } = etag_files_count,
+        "content etags"
+    );
+    Ok(())
+}
+
+fn list_doc_root_files_excluding(doc_root: &Path, excluded_paths: &[PathBuf]) -> Vec<PathBuf> {
+    list_doc_root_files(doc_root)
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| !excluded_paths.contains(path))
+        .collect()
+}
+
 // Load a table of Build Plan [requires.metadata] from context.
 // When a key is defined multiple times,
 // * for tables: insert the new row value to the existing table
@@ -184,12 +470,36 @@ fn generate_config_with_inheritance(
     Ok(heroku_config)
 }
 
+fn runtime_config_doc_roots(
+    heroku_config: &HerokuWebServerConfig,
+    doc_root_path: &Path,
+    doc_index: &str,
+) -> Vec<(PathBuf, String)> {
+    let mut doc_roots = vec![(doc_root_path.to_path_buf(), doc_index.to_string())];
+    for mount in heroku_config.mounts.iter().flatten() {
+        doc_roots.push((
+            mount.root.clone(),
+            mount.index.clone().unwrap_or(DEFAULT_DOC_INDEX.to_string()),
+        ));
+    }
+    doc_roots
+}
+
 fn list_runtime_config_target_files(
     real_root_path: &Path,
     doc_root_path: &Path,
     doc_index: &str,
     runtime_config: &RuntimeConfig,
 ) -> String {
+    runtime_config_target_files(real_root_path, doc_root_path, doc_index, runtime_config).join(", ")
+}
+
+fn runtime_config_target_files(
+    real_root_path: &Path,
+    doc_root_path: &Path,
+    doc_index: &str,
+    runtime_config: &RuntimeConfig,
+) -> Vec<String> {
     runtime_config
         .html_files
         .clone()
@@ -217,9 +527,6 @@ fn list_runtime_config_target_files(
             }
             acc
         })
-        .into_iter()
-        .collect::<Vec<_>>()
-        .join(", ")
 }
 
 #[cfg(test)]
@@ -243,9 +550,11 @@ mod tests {
     use crate::{
         config_web_server::{
             generate_build_plan_config, generate_config_with_inheritance,
-            list_runtime_config_target_files,
+            list_runtime_config_target_files, runtime_config_doc_roots,
+        },
+        heroku_web_server_config::{
+            HerokuWebServerConfig, MountConfig, RuntimeConfig, DEFAULT_DOC_INDEX, DEFAULT_DOC_ROOT,
         },
-        heroku_web_server_config::{RuntimeConfig, DEFAULT_DOC_INDEX, DEFAULT_DOC_ROOT},
         StaticWebServerBuildpack, BUILD_PLAN_ID,
     };
 
@@ -500,4 +809,36 @@ mod tests {
         );
         assert_eq!("", result);
     }
+
+    #[test]
+    fn runtime_config_doc_roots_include_mounts() {
+        let heroku_config = HerokuWebServerConfig {
+            mounts: Some(vec![
+                MountConfig {
+                    path: "/docs/".to_string(),
+                    root: PathBuf::from("docs/build"),
+                    ..MountConfig::default()
+                },
+                MountConfig {
+                    path: "/app/".to_string(),
+                    root: PathBuf::from("app/dist"),
+                    index: Some("app.html".to_string()),
+                    ..MountConfig::default()
+                },
+            ]),
+            ..HerokuWebServerConfig::default()
+        };
+        assert_eq!(
+            runtime_config_doc_roots(
+                &heroku_config,
+                Path::new(DEFAULT_DOC_ROOT),
+                DEFAULT_DOC_INDEX
+            ),
+            vec![
+                (PathBuf::from("public"), "index.html".to_string()),
+                (PathBuf::from("docs/build"), "index.html".to_string()),
+                (PathBuf::from("app/dist"), "app.html".to_string()),
+            ]
+        );
+    }
 }
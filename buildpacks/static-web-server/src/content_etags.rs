@@ -0,0 +1,65 @@
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extension of the files holding each served file's `ETag`, read by Caddy's `file_server`.
+pub(crate) const ETAG_FILE_EXTENSION: &str = ".etag";
+
+/// Writes a `.etag` sibling for each file, containing a strong `ETag` derived from the file's
+/// contents. Returns how many `.etag` files were written.
+pub(crate) fn write_etag_files(files: &[PathBuf]) -> Result<usize, std::io::Error> {
+    let mut count = 0;
+    for file in files.iter().filter(|f| !is_etag_file(f)) {
+        let contents = fs::read(file)?;
+        let mut etag_path = file.as_os_str().to_owned();
+        etag_path.push(ETAG_FILE_EXTENSION);
+        fs::write(etag_path, content_etag(&contents))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn is_etag_file(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(ETAG_FILE_EXTENSION)
+}
+
+// A quoted, truncated SHA-256 digest, so the ETag changes whenever content changes,
+// regardless of the normalized modification times in the container image.
+fn content_etag(contents: &[u8]) -> String {
+    let digest = Sha256::digest(contents);
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_content_etag() {
+        assert_eq!(
+            content_etag(b"Hello, world!"),
+            "\"315f5bdb76d078c43b8ac0064e4a0164\""
+        );
+        assert_ne!(
+            content_etag(b"Hello, world!"),
+            content_etag(b"Hello, world?")
+        );
+    }
+
+    #[test]
+    fn writes_etag_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.js");
+        fs::write(&file, "Hello, world!").unwrap();
+        let existing_etag_file = dir.path().join("other.js.etag");
+        fs::write(&existing_etag_file, "\"existing\"").unwrap();
+
+        let count = write_etag_files(&[file, existing_etag_file]).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("app.js.etag")).unwrap(),
+            "\"315f5bdb76d078c43b8ac0064e4a0164\""
+        );
+        assert!(!dir.path().join("other.js.etag.etag").exists());
+    }
+}
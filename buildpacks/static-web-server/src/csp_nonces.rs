@@ -0,0 +1,113 @@
+use crate::inline_hashes::is_html_file;
+use html5ever::driver::ParseOpts;
+use html5ever::serialize::SerializeOpts;
+use html5ever::tendril::TendrilSink;
+use html5ever::{ns, parse_document, serialize, Attribute, LocalName, QualName};
+use markup5ever_rcdom::{Handle, NodeData, RcDom, SerializableHandle};
+use std::fs;
+use std::path::PathBuf;
+
+/// Caddy placeholder for the per-request nonce, shared by the response header & the HTML body.
+pub(crate) const NONCE_PLACEHOLDER: &str = "{http.request.uuid}";
+
+/// Delimiters of the Caddy template tags written into HTML, distinct from the default `{{ }}`,
+/// so that any other curly braces in the built HTML are not evaluated as templates.
+pub(crate) const NONCE_TEMPLATE_DELIMITERS: [&str; 2] = ["{{csp-nonce", "}}"];
+
+/// Sets a `nonce` attribute, as a Caddy template tag of the per-request nonce, on the inline
+/// scripts & styles of each HTML file. Returns the files that were rewritten.
+pub(crate) fn write_nonce_placeholders(files: &[PathBuf]) -> Result<Vec<PathBuf>, std::io::Error> {
+    let mut rewritten_files = vec![];
+    for file in files.iter().filter(|f| is_html_file(f)) {
+        let html_bytes = fs::read(file)?;
+        if let Some(html) = html_with_nonce_placeholders(&String::from_utf8_lossy(&html_bytes))? {
+            fs::write(file, html)?;
+            rewritten_files.push(file.clone());
+        }
+    }
+    Ok(rewritten_files)
+}
+
+fn html_with_nonce_placeholders(html: &str) -> Result<Option<String>, std::io::Error> {
+    let dom = parse_document(RcDom::default(), ParseOpts::default()).one(html);
+    if !set_nonce_attrs(&dom.document) {
+        return Ok(None);
+    }
+    let document: SerializableHandle = dom.document.clone().into();
+    let mut buf = Vec::new();
+    serialize(&mut buf, &document, SerializeOpts::default())?;
+    Ok(Some(String::from_utf8_lossy(&buf).into_owned()))
+}
+
+// Matches the inline content hashed for Content-Security-Policy; scripts with a `src` are
+// covered by the policy's source list instead.
+fn set_nonce_attrs(node: &Handle) -> bool {
+    let mut is_set = false;
+    if let NodeData::Element {
+        ref name,
+        ref attrs,
+        ..
+    } = node.data
+    {
+        let mut attrs = attrs.borrow_mut();
+        let has_src = attrs.iter().any(|a| &*a.name.local == "src");
+        let is_inline = name.ns == ns!(html)
+            && (&*name.local == "style" || (&*name.local == "script" && !has_src));
+        if is_inline {
+            attrs.retain(|a| &*a.name.local != "nonce");
+            attrs.push(Attribute {
+                name: QualName::new(None, ns!(), LocalName::from("nonce")),
+                value: nonce_template_tag().into(),
+            });
+            is_set = true;
+        }
+    }
+    for child in node.children.borrow().iter() {
+        is_set |= set_nonce_attrs(child);
+    }
+    is_set
+}
+
+// Backtick-quoted, because the serializer escapes double quotes within attribute values.
+fn nonce_template_tag() -> String {
+    let [open, close] = NONCE_TEMPLATE_DELIMITERS;
+    let placeholder = NONCE_PLACEHOLDER.trim_matches(['{', '}']);
+    format!("{open} placeholder `{placeholder}` {close}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_nonce_placeholders_on_inline_scripts_and_styles() {
+        let html = html_with_nonce_placeholders(
+            r#"<!DOCTYPE html>
+<html><head>
+<style>body { color: black; }</style>
+<script src="/assets/index.js"></script>
+<script nonce="stale">alert('Hello, world.');</script>
+</head><body></body></html>"#,
+        )
+        .unwrap()
+        .expect("should rewrite the HTML");
+
+        assert_eq!(
+            html,
+            r#"<!DOCTYPE html><html><head>
+<style nonce="{{csp-nonce placeholder `http.request.uuid` }}">body { color: black; }</style>
+<script src="/assets/index.js"></script>
+<script nonce="{{csp-nonce placeholder `http.request.uuid` }}">alert('Hello, world.');</script>
+</head><body></body></html>"#
+        );
+    }
+
+    #[test]
+    fn leaves_html_without_inline_content() {
+        let html = html_with_nonce_placeholders(
+            r#"<html><head><script src="/app.js"></script></head><body></body></html>"#,
+        )
+        .unwrap();
+        assert_eq!(html, None);
+    }
+}
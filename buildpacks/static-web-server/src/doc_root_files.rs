@@ -0,0 +1,139 @@
+use crate::inline_hashes::InlineHashes;
+use glob::{glob, Pattern};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Facts about the files in the document root, which the generated web server
+/// configuration depends on. Collected during build, after any static build command.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct DocRootFiles {
+    /// URL paths of files with a content hash in their name.
+    pub(crate) fingerprinted_paths: Vec<String>,
+    /// Hashes of inline scripts & styles, keyed by the URL path of each HTML file.
+    pub(crate) inline_hashes: BTreeMap<String, InlineHashes>,
+}
+
+/// Lists the regular files within the document root, paired with the URL path each is served at.
+pub(crate) fn list_doc_root_files(doc_root: &Path) -> Vec<(PathBuf, String)> {
+    let pattern = format!("{}/**/*", Pattern::escape(&doc_root.to_string_lossy()));
+    glob(&pattern)
+        .expect("escaped doc root should always form a valid glob pattern")
+        .flatten()
+        .filter(|path| path.is_file())
+        .filter_map(|path| {
+            let relative_path = path.strip_prefix(doc_root).ok()?;
+            let url_path = relative_path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .fold(String::new(), |acc, c| format!("{acc}/{c}"));
+            Some((path, url_path))
+        })
+        .collect()
+}
+
+pub(crate) fn find_fingerprinted_paths(doc_root: &Path) -> Vec<String> {
+    list_doc_root_files(doc_root)
+        .into_iter()
+        .filter(|(path, _)| {
+            path.file_name()
+                .is_some_and(|file_name| is_fingerprinted(&file_name.to_string_lossy()))
+        })
+        .map(|(_, url_path)| url_path)
+        .collect()
+}
+
+static HEX_HASH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:^|[.-])([0-9a-f]{8,})\.").expect("hex hash regex should compile")
+});
+static BASE64URL_HASH_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"-([A-Za-z0-9_]{8})\.[A-Za-z0-9]+$").expect("base64url hash regex should compile")
+});
+
+// Detects the content hashes that frontend build tools put in file names:
+// * hex, such as webpack/CRA `main.3f2a1b9c.chunk.js`, Ember `vendor-d41d8cd98f00b204.js`,
+//   or Next.js `8a8f7e1c2b3d4e5f.css`
+// * base64url, such as Vite `index-B2x_9Abc.js`
+// Hashes must mix letters with digits, so that words, dates, and version numbers in
+// hand-named files are not mistaken for hashes. A base64url hash containing `-` is not
+// detected, because it cannot be told apart from hyphenated names like `logo-white-01.svg`,
+// so that file is only revalidated instead of cached forever.
+fn is_fingerprinted(file_name: &str) -> bool {
+    let is_hash = |s: &str| {
+        s.chars().any(|c| c.is_ascii_alphabetic()) && s.chars().any(|c| c.is_ascii_digit())
+    };
+
+    HEX_HASH_REGEX
+        .captures_iter(file_name)
+        .any(|caps| is_hash(&caps[1]))
+        || BASE64URL_HASH_REGEX
+            .captures(file_name)
+            .is_some_and(|caps| is_hash(&caps[1]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_fingerprinted_file_names() {
+        for file_name in [
+            "index-B2x_9Abc.js",
+            "main.3f2a1b9c.chunk.js",
+            "main.3f2a1b9c.js",
+            "logo.6ce24c58023cc2f8fd88fe9d219db6c6.svg",
+            "vendor-d41d8cd98f00b204e9800998ecf8427e.js",
+            "8a8f7e1c2b3d4e5f.css",
+            "page-9f8e7d6c5b4a3210.js",
+        ] {
+            assert!(is_fingerprinted(file_name), "{file_name} is fingerprinted");
+        }
+    }
+
+    #[test]
+    fn ignores_hand_named_files() {
+        for file_name in [
+            "index.html",
+            "favicon.ico",
+            "robots.txt",
+            "jquery-3.6.0.min.js",
+            "font-awesome-4.woff",
+            "bootstrap-v5-theme1.js",
+            "app-settings.js",
+            "report-20240101.pdf",
+            "deadbeef.txt",
+            "MyWidget.js",
+            "logo-white-01.svg",
+            "nav-menu-v10.js",
+            "app-mobile-1.js",
+            "icon-arrow_left.svg",
+            // Hyphenated base64url hashes are not told apart from hand-named files.
+            "index-Dq4k-bTE.css",
+        ] {
+            assert!(
+                !is_fingerprinted(file_name),
+                "{file_name} is not fingerprinted"
+            );
+        }
+    }
+
+    #[test]
+    fn finds_fingerprinted_paths() {
+        let mut paths = find_fingerprinted_paths(Path::new("tests/fixtures/caching/public"));
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "/assets/index-B2x_9Abc.js".to_string(),
+                "/assets/index-Dq4k7bTE.css".to_string(),
+                "/static/js/main.3f2a1b9c.chunk.js".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_nothing_in_nonexistent_doc_root() {
+        assert!(find_fingerprinted_paths(Path::new("tests/fixtures/non-existent")).is_empty());
+    }
+}
@@ -21,8 +21,15 @@ pub(crate) enum StaticWebServerBuildpackError {
     CannotCreateCaddyInstallationDir(std::io::Error),
     CannotCreateCaddyTarballFile(std::io::Error),
     BuildCommandFailed(std::io::Error),
+    CannotPrecompressFile(std::io::Error),
+    CannotWriteEtagFile(std::io::Error),
+    CannotHashInlineContent(std::io::Error),
+    CannotWriteNoncePlaceholders(std::io::Error),
     CannotCreateWebExecD(std::io::Error),
     CannotInstallEnvAsHtmlData(std::io::Error),
+    CannotInstallCaddyConfigEnv(std::io::Error),
+    CannotInstallBasicAuthPasswordBcrypt(std::io::Error),
+    CannotReadBasicAuthUsersFile(String),
     ConfigurationConstraint(String),
     ChecksumVerificationFailed { expected: Vec<u8>, actual: Vec<u8> },
     ReadDownloadForChecksum(std::io::Error),
@@ -105,6 +112,34 @@ fn buildpack_error_message(error: StaticWebServerBuildpackError) -> ErrorMessage
             error_string: e.to_string(),
             error_id: "build_command_failed_error".to_string(),
         },
+        StaticWebServerBuildpackError::CannotPrecompressFile(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot precompress files in the document root for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e.to_string(),
+            error_id: "cannot_precompress_file_error".to_string(),
+        },
+        StaticWebServerBuildpackError::CannotWriteEtagFile(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot write content ETag files in the document root for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e.to_string(),
+            error_id: "cannot_write_etag_file_error".to_string(),
+        },
+        StaticWebServerBuildpackError::CannotHashInlineContent(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot hash inline scripts & styles of HTML files in the document root for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e.to_string(),
+            error_id: "cannot_hash_inline_content_error".to_string(),
+        },
+        StaticWebServerBuildpackError::CannotWriteNoncePlaceholders(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot write Content-Security-Policy nonce placeholders into HTML files in the document root for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e.to_string(),
+            error_id: "cannot_write_nonce_placeholders_error".to_string(),
+        },
         StaticWebServerBuildpackError::CannotCreateWebExecD(e) => ErrorMessage {
             message: formatdoc! {"
                 Cannot create exec.d/web for {buildpack_name}
@@ -119,6 +154,27 @@ fn buildpack_error_message(error: StaticWebServerBuildpackError) -> ErrorMessage
             error_string: e.to_string(),
             error_id: "cannot_install_env_as_html_data_error".to_string(),
         },
+        StaticWebServerBuildpackError::CannotInstallCaddyConfigEnv(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot install caddy-config-env (launch-time Caddy configuration program) for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e.to_string(),
+            error_id: "cannot_install_caddy_config_env_error".to_string(),
+        },
+        StaticWebServerBuildpackError::CannotInstallBasicAuthPasswordBcrypt(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot install basic-auth-password-bcrypt (launch-time password hashing program) for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e.to_string(),
+            error_id: "cannot_install_basic_auth_password_bcrypt_error".to_string(),
+        },
+        StaticWebServerBuildpackError::CannotReadBasicAuthUsersFile(e) => ErrorMessage {
+            message: formatdoc! {"
+                Cannot read Basic Auth users file for {buildpack_name}
+            ", buildpack_name = style::value(BUILDPACK_NAME) },
+            error_string: e,
+            error_id: "cannot_read_basic_auth_users_file_error".to_string(),
+        },
         StaticWebServerBuildpackError::CannotParseHerokuWebServerConfiguration(e) => ErrorMessage {
             message: formatdoc! {"
                 Cannot parse Heroku web server configuration for {buildpack_name}
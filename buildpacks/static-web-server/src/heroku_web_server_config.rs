@@ -6,23 +6,55 @@ use std::path::PathBuf;
 
 pub(crate) const DEFAULT_DOC_ROOT: &str = "public";
 pub(crate) const DEFAULT_DOC_INDEX: &str = "index.html";
+// Error statuses that the server responds with by itself, so that custom pages for any other
+// status must list the `paths` that respond with it.
+pub(crate) const SERVER_ERROR_STATUS_CODES: &[u16] = &[401, 403, 404, 500, 502, 503, 504];
 
 #[derive(Deserialize, Debug, Default, Clone)]
 pub(crate) struct HerokuWebServerConfig {
     pub(crate) build: Option<Executable>,
     pub(crate) root: Option<PathBuf>,
     pub(crate) index: Option<String>,
+    pub(crate) access: Option<AccessConfig>,
+    pub(crate) forward_auth: Option<ForwardAuthConfig>,
+    pub(crate) maintenance: Option<MaintenanceConfig>,
     pub(crate) errors: Option<ErrorsConfig>,
+    pub(crate) spa_fallback: Option<SpaFallbackConfig>,
     #[serde(default, deserialize_with = "deserialize_path_matched_headers")]
     pub(crate) headers: Option<Vec<PathMatchedHeader>>,
+    pub(crate) security_headers: Option<SecurityHeadersPreset>,
+    pub(crate) content_security_policy: Option<ContentSecurityPolicyConfig>,
+    #[serde(default, deserialize_with = "deserialize_one_or_many")]
+    pub(crate) cors: Option<Vec<CorsConfig>>,
     pub(crate) runtime_config: Option<RuntimeConfig>,
+    pub(crate) caching: Option<CachingMode>,
+    pub(crate) precompress: Option<bool>,
+    pub(crate) content_etags: Option<bool>,
+    pub(crate) force_https: Option<ForceHttpsConfig>,
+    pub(crate) canonical_host: Option<CanonicalHostConfig>,
+    pub(crate) trailing_slash: Option<TrailingSlashMode>,
+    pub(crate) redirects: Option<Vec<RedirectConfig>>,
+    pub(crate) rewrites: Option<Vec<RewriteConfig>>,
+    pub(crate) proxies: Option<Vec<ProxyConfig>>,
+    pub(crate) mounts: Option<Vec<MountConfig>>,
+    pub(crate) sites: Option<Vec<SiteConfig>>,
     pub(crate) caddy_server_opts: Option<CaddyServerOpts>,
 }
 
-#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+/// A site served for its hosts from its own document root, instead of the top-level one.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub(crate) struct SiteConfig {
+    pub(crate) hosts: Vec<String>,
+    pub(crate) root: Option<PathBuf>,
+    pub(crate) index: Option<String>,
+    pub(crate) errors: Option<ErrorsConfig>,
+    #[serde(default, deserialize_with = "deserialize_path_matched_headers")]
+    pub(crate) headers: Option<Vec<PathMatchedHeader>>,
+}
+
+#[derive(Eq, PartialEq, Debug, Default, Clone)]
 pub(crate) struct ErrorsConfig {
-    #[serde(rename = "404")]
-    pub(crate) custom_404_page: Option<ErrorConfig>,
+    pub(crate) custom_pages: BTreeMap<u16, ErrorConfig>,
 }
 
 #[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
@@ -30,6 +62,32 @@ pub(crate) struct ErrorConfig {
     pub(crate) file_path: PathBuf,
     pub(crate) status: Option<u16>,
     pub(crate) path_exclusions: Option<Vec<String>>,
+    pub(crate) paths: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct AccessConfig {
+    pub(crate) allow: Option<Vec<String>>,
+    pub(crate) allow_env: Option<String>,
+    pub(crate) deny: Option<Vec<String>>,
+    pub(crate) deny_env: Option<String>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct ForwardAuthConfig {
+    pub(crate) url_env: Option<String>,
+    pub(crate) copy_headers: Option<Vec<String>>,
+    pub(crate) path_exclusions: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct MaintenanceConfig {
+    pub(crate) file_path: Option<PathBuf>,
+    pub(crate) status: Option<u16>,
+    pub(crate) retry_after: Option<u32>,
+    pub(crate) bypass_ips: Option<Vec<String>>,
+    pub(crate) bypass_cookie: Option<String>,
+    pub(crate) path_exclusions: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
@@ -41,23 +99,259 @@ pub(crate) struct Executable {
 #[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
 pub(crate) struct PathMatchedHeader {
     pub(crate) path_matcher: String,
+    pub(crate) matcher: HeaderMatcher,
+    pub(crate) target: HeaderTarget,
+    pub(crate) operation: HeaderOperation,
     pub(crate) key: String,
     pub(crate) value: String,
 }
 
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HeaderTarget {
+    Request,
+    #[default]
+    Response,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum HeaderOperation {
+    #[default]
+    Set,
+    Add,
+    Delete,
+}
+
+// A path's header rule, or multiple rules as an array of tables, such as for different matchers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PathHeaderRules {
+    One(BTreeMap<String, PathHeaderValue>),
+    Many(Vec<BTreeMap<String, PathHeaderValue>>),
+}
+
+// A header value, the `request` or `response` header operations, or the `match` of a rule.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PathHeaderValue {
+    Value(String),
+    Operations(HeaderOperationsConfig),
+    Matcher(HeaderMatcher),
+}
+
+/// Matches a header rule on more than the request path. The content type & status match the
+/// response, so only its headers are changed, after the response is written.
+#[derive(Deserialize, Eq, PartialEq, Hash, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct HeaderMatcher {
+    pub(crate) host: Option<Vec<String>>,
+    pub(crate) content_type: Option<Vec<String>>,
+    pub(crate) status: Option<Vec<u16>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HeaderOperationsConfig {
+    set: Option<BTreeMap<String, String>>,
+    add: Option<BTreeMap<String, HeaderValues>>,
+    delete: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HeaderValues {
+    One(String),
+    Many(Vec<String>),
+}
+
 #[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
 pub(crate) struct RuntimeConfig {
     pub(crate) enabled: Option<bool>,
     pub(crate) html_files: Option<Vec<String>>,
 }
 
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SecurityHeadersPreset {
+    Recommended,
+    Strict,
+    #[default]
+    Off,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct ContentSecurityPolicyConfig {
+    pub(crate) policy: String,
+    pub(crate) report_only: Option<bool>,
+    pub(crate) nonce: Option<bool>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct CorsConfig {
+    pub(crate) path_matcher: String,
+    pub(crate) allowed_origins: Option<Vec<String>>,
+    pub(crate) allowed_origins_env: Option<String>,
+    pub(crate) allowed_methods: Option<Vec<String>>,
+    pub(crate) allowed_headers: Option<Vec<String>>,
+    pub(crate) allow_credentials: Option<bool>,
+    pub(crate) max_age: Option<u32>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CachingMode {
+    Auto,
+    #[default]
+    Off,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct ForceHttpsConfig {
+    pub(crate) status: Option<u16>,
+    pub(crate) path_exclusions: Option<Vec<String>>,
+    pub(crate) hsts: Option<HstsConfig>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct HstsConfig {
+    pub(crate) max_age: Option<u32>,
+    pub(crate) include_subdomains: Option<bool>,
+    pub(crate) preload: Option<bool>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct CanonicalHostConfig {
+    pub(crate) host: Option<String>,
+    pub(crate) host_env: Option<String>,
+    pub(crate) status: Option<u16>,
+    pub(crate) path_exclusions: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TrailingSlashMode {
+    Always,
+    Never,
+    #[default]
+    Ignore,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct RedirectConfig {
+    pub(crate) from: Option<String>,
+    pub(crate) from_regex: Option<String>,
+    pub(crate) to: String,
+    pub(crate) status: Option<u16>,
+    pub(crate) preserve_query: Option<bool>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct RewriteConfig {
+    pub(crate) from: Option<String>,
+    pub(crate) from_regex: Option<String>,
+    pub(crate) to: String,
+    pub(crate) if_target_exists: Option<bool>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct ProxyConfig {
+    pub(crate) path_matcher: String,
+    pub(crate) upstream: String,
+    pub(crate) strip_path_prefix: Option<String>,
+    pub(crate) host_header: Option<String>,
+    pub(crate) dial_timeout: Option<String>,
+    pub(crate) response_header_timeout: Option<String>,
+}
+
+/// A document root served under a URL path prefix, alongside the top-level one.
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct MountConfig {
+    pub(crate) path: String,
+    pub(crate) root: PathBuf,
+    pub(crate) index: Option<String>,
+    pub(crate) spa_fallback: Option<SpaFallbackConfig>,
+    pub(crate) errors: Option<ErrorsConfig>,
+}
+
+/// Either `spa_fallback = true`, falling back to the index document, or a table of options.
+#[derive(Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum SpaFallbackConfig {
+    Enabled(bool),
+    Options(SpaFallbackOpts),
+}
+
+impl SpaFallbackConfig {
+    pub(crate) fn is_enabled(&self) -> bool {
+        match self {
+            SpaFallbackConfig::Enabled(enabled) => *enabled,
+            SpaFallbackConfig::Options(opts) => opts.enabled.unwrap_or(true),
+        }
+    }
+
+    pub(crate) fn opts(&self) -> Option<&SpaFallbackOpts> {
+        match self {
+            SpaFallbackConfig::Enabled(_) => None,
+            SpaFallbackConfig::Options(opts) => Some(opts),
+        }
+    }
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct SpaFallbackOpts {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) file_path: Option<PathBuf>,
+    pub(crate) path_exclusions: Option<Vec<String>>,
+}
+
 #[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
 pub(crate) struct CaddyServerOpts {
     pub(crate) templates: Option<bool>,
     pub(crate) access_logs: Option<CaddyAccessLogsConfig>,
-    pub(crate) basic_auth: Option<bool>,
+    pub(crate) basic_auth: Option<CaddyBasicAuthConfig>,
     pub(crate) clean_urls: Option<bool>,
     pub(crate) static_responses: Option<Vec<CaddyStaticResponseConfig>>,
+    pub(crate) trusted_proxies: Option<CaddyTrustedProxiesConfig>,
+}
+
+/// Either `basic_auth = true`, or a table of options.
+#[derive(Deserialize, Eq, PartialEq, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum CaddyBasicAuthConfig {
+    Enabled(bool),
+    Options(CaddyBasicAuthOpts),
+}
+
+impl CaddyBasicAuthConfig {
+    pub(crate) fn is_enabled(&self) -> bool {
+        match self {
+            CaddyBasicAuthConfig::Enabled(enabled) => *enabled,
+            CaddyBasicAuthConfig::Options(opts) => opts.enabled.unwrap_or(true),
+        }
+    }
+
+    pub(crate) fn opts(&self) -> Option<&CaddyBasicAuthOpts> {
+        match self {
+            CaddyBasicAuthConfig::Enabled(_) => None,
+            CaddyBasicAuthConfig::Options(opts) => Some(opts),
+        }
+    }
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct CaddyBasicAuthOpts {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) paths: Option<Vec<String>>,
+    pub(crate) exclude_paths: Option<Vec<String>>,
+    pub(crate) users_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
+pub(crate) struct CaddyTrustedProxiesConfig {
+    pub(crate) enabled: Option<bool>,
+    pub(crate) ranges: Option<Vec<String>>,
+    pub(crate) client_ip_headers: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Eq, PartialEq, Debug, Default, Clone)]
@@ -122,6 +416,74 @@ impl<'de> Visitor<'de> for HeadersVisitor {
     }
 }
 
+impl<'de> Deserialize<'de> for ErrorsConfig {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        d.deserialize_map(ErrorsVisitor)
+    }
+}
+
+struct ErrorsVisitor;
+
+impl<'de> Visitor<'de> for ErrorsVisitor {
+    type Value = ErrorsConfig;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "a map of HTTP error status codes to error pages")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut custom_pages = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry::<String, ErrorConfig>()? {
+            let status_code = key
+                .parse::<u16>()
+                .ok()
+                .filter(|code| (400..=599).contains(code))
+                .ok_or_else(|| {
+                    serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(&key),
+                        &"an HTTP error status code, 400-599",
+                    )
+                })?;
+            if !SERVER_ERROR_STATUS_CODES.contains(&status_code)
+                && value.paths.as_ref().is_none_or(Vec::is_empty)
+            {
+                return Err(serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Str(&key),
+                    &"an error status the server responds with, 401, 403, 404, 500, 502, 503, or 504, or any 400-599 status with `paths` that respond with it",
+                ));
+            }
+            custom_pages.insert(status_code, value);
+        }
+
+        Ok(ErrorsConfig { custom_pages })
+    }
+}
+
+// Either a single table, like `[cors]`, or an array of tables, like `[[cors]]`.
+fn deserialize_one_or_many<'de, D, T>(d: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(Some(match OneOrMany::deserialize(d)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    }))
+}
+
 fn deserialize_path_matched_headers<'de, D>(
     d: D,
 ) -> Result<Option<Vec<PathMatchedHeader>>, D::Error>
@@ -151,18 +513,106 @@ impl<'de> Visitor<'de> for PathMatchedHeadersVisitor {
         A: MapAccess<'de>,
     {
         let mut result = vec![];
-        while let Some((key, value)) = map.next_entry::<String, BTreeMap<String, String>>()? {
-            for (header_key, header_value) in value {
+        while let Some((key, value)) = map.next_entry::<String, PathHeaderRules>()? {
+            let rules = match value {
+                PathHeaderRules::One(rule) => vec![rule],
+                PathHeaderRules::Many(rules) => rules,
+            };
+            for rule in rules {
+                push_path_header_rule(&key, rule, &mut result).map_err(serde::de::Error::custom)?;
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn push_path_header_rule(
+    path_matcher: &str,
+    mut rule: BTreeMap<String, PathHeaderValue>,
+    result: &mut Vec<PathMatchedHeader>,
+) -> Result<(), String> {
+    let matcher = match rule.remove("match") {
+        None => HeaderMatcher::default(),
+        Some(PathHeaderValue::Matcher(matcher))
+            if matcher
+                .status
+                .iter()
+                .flatten()
+                .all(|status| (100..=599).contains(status)) =>
+        {
+            matcher
+        }
+        Some(_) => {
+            return Err(format!(
+                "headers.\"{path_matcher}\".match must be a table of host, content_type, or status from 100 to 599"
+            ))
+        }
+    };
+    for (header_key, header_value) in rule {
+        let operations = match header_value {
+            // A header value sets the response header, as it always has.
+            PathHeaderValue::Value(header_value) => {
                 result.push(PathMatchedHeader {
-                    path_matcher: key.clone(),
+                    path_matcher: path_matcher.to_string(),
+                    matcher: matcher.clone(),
                     key: header_key,
                     value: header_value,
+                    ..PathMatchedHeader::default()
                 });
+                continue;
+            }
+            PathHeaderValue::Operations(operations) => operations,
+            PathHeaderValue::Matcher(_) => {
+                return Err(format!(
+                    "headers.\"{path_matcher}\".{header_key} must be a header value, or request or response header operations"
+                ))
             }
+        };
+        let target = match header_key.as_str() {
+            "request" => HeaderTarget::Request,
+            "response" => HeaderTarget::Response,
+            _ => {
+                return Err(format!(
+                    "headers.\"{path_matcher}\".{header_key} must be a header value, or request or response header operations"
+                ))
+            }
+        };
+        // Content type & status are only known for the response.
+        if target == HeaderTarget::Request
+            && (matcher.content_type.is_some() || matcher.status.is_some())
+        {
+            return Err(format!(
+                "headers.\"{path_matcher}\".request operations cannot match content_type or status"
+            ));
+        }
+        let mut push = |operation, header_key: String, header_value: String| {
+            result.push(PathMatchedHeader {
+                path_matcher: path_matcher.to_string(),
+                matcher: matcher.clone(),
+                target,
+                operation,
+                key: header_key,
+                value: header_value,
+            });
+        };
+        for (header_key, header_value) in operations.set.unwrap_or_default() {
+            push(HeaderOperation::Set, header_key, header_value);
+        }
+        for (header_key, header_values) in operations.add.unwrap_or_default() {
+            let header_values = match header_values {
+                HeaderValues::One(header_value) => vec![header_value],
+                HeaderValues::Many(header_values) => header_values,
+            };
+            for header_value in header_values {
+                push(HeaderOperation::Add, header_key.clone(), header_value);
+            }
+        }
+        for header_key in operations.delete.unwrap_or_default() {
+            push(HeaderOperation::Delete, header_key, String::new());
         }
-
-        Ok(result)
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -185,11 +635,15 @@ mod tests {
         assert_eq!(
             parsed_config.errors,
             Some(ErrorsConfig {
-                custom_404_page: Some(ErrorConfig {
-                    file_path: PathBuf::from("error-404.html"),
-                    status: None,
-                    path_exclusions: None,
-                }),
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("error-404.html"),
+                        status: None,
+                        path_exclusions: None,
+                        paths: None,
+                    }
+                )]),
             })
         );
     }
@@ -207,15 +661,100 @@ mod tests {
         assert_eq!(
             parsed_config.errors,
             Some(ErrorsConfig {
-                custom_404_page: Some(ErrorConfig {
-                    file_path: PathBuf::from("index.html"),
-                    status: Some(200),
-                    path_exclusions: Some(vec!["/assets/*".to_string(), "/static/*".to_string()]),
-                }),
+                custom_pages: BTreeMap::from([(
+                    404,
+                    ErrorConfig {
+                        file_path: PathBuf::from("index.html"),
+                        status: Some(200),
+                        path_exclusions: Some(vec![
+                            "/assets/*".to_string(),
+                            "/static/*".to_string()
+                        ]),
+                        paths: None,
+                    }
+                )]),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_errors_with_any_status_code() {
+        let toml_str = r#"
+            [errors]
+            401.file_path = "error-401.html"
+            404.file_path = "error-404.html"
+            410.file_path = "error-410.html"
+            410.paths = ["/old-campaign/*"]
+            503.file_path = "maintenance.html"
+            503.path_exclusions = ["/api/*"]
+        "#;
+
+        let parsed_config: HerokuWebServerConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(
+            parsed_config.errors,
+            Some(ErrorsConfig {
+                custom_pages: BTreeMap::from([
+                    (
+                        401,
+                        ErrorConfig {
+                            file_path: PathBuf::from("error-401.html"),
+                            status: None,
+                            path_exclusions: None,
+                            paths: None,
+                        }
+                    ),
+                    (
+                        404,
+                        ErrorConfig {
+                            file_path: PathBuf::from("error-404.html"),
+                            status: None,
+                            path_exclusions: None,
+                            paths: None,
+                        }
+                    ),
+                    (
+                        410,
+                        ErrorConfig {
+                            file_path: PathBuf::from("error-410.html"),
+                            status: None,
+                            path_exclusions: None,
+                            paths: Some(vec!["/old-campaign/*".to_string()]),
+                        }
+                    ),
+                    (
+                        503,
+                        ErrorConfig {
+                            file_path: PathBuf::from("maintenance.html"),
+                            status: None,
+                            path_exclusions: Some(vec!["/api/*".to_string()]),
+                            paths: None,
+                        }
+                    ),
+                ]),
             })
         );
     }
 
+    #[test]
+    fn custom_errors_with_invalid_status_code() {
+        let toml_str = r#"
+            [errors]
+            200.file_path = "index.html"
+        "#;
+
+        let parsed_config = toml::from_str::<HerokuWebServerConfig>(toml_str);
+        assert!(parsed_config.is_err());
+
+        // The server never responds with 410 by itself, so its page needs paths to respond with it.
+        let toml_str = r#"
+            [errors]
+            410.file_path = "error-410.html"
+        "#;
+
+        let parsed_config = toml::from_str::<HerokuWebServerConfig>(toml_str);
+        assert!(parsed_config.is_err());
+    }
+
     #[test]
     fn build_command() {
         let toml_config = toml! {
@@ -271,6 +810,48 @@ mod tests {
         assert_eq!(parsed_config.errors, None);
     }
 
+    #[test]
+    fn custom_caddy_server_opts_trusted_proxies() {
+        let toml_config = toml! {
+            [caddy_server_opts.trusted_proxies]
+            ranges = ["10.0.0.0/8"]
+            client_ip_headers = ["X-Real-IP"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.caddy_server_opts.unwrap().trusted_proxies,
+            Some(CaddyTrustedProxiesConfig {
+                enabled: None,
+                ranges: Some(vec!["10.0.0.0/8".to_string()]),
+                client_ip_headers: Some(vec!["X-Real-IP".to_string()]),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_caddy_server_opts_basic_auth() {
+        let toml_config = toml! {
+            [caddy_server_opts.basic_auth]
+            paths = ["/admin/*"]
+            exclude_paths = ["/admin/health"]
+            users_file = "config/basic-auth-users"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        let basic_auth = parsed_config.caddy_server_opts.unwrap().basic_auth.unwrap();
+        assert!(basic_auth.is_enabled());
+        assert_eq!(
+            basic_auth.opts(),
+            Some(&CaddyBasicAuthOpts {
+                enabled: None,
+                paths: Some(vec!["/admin/*".to_string()]),
+                exclude_paths: Some(vec!["/admin/health".to_string()]),
+                users_file: Some(PathBuf::from("config/basic-auth-users")),
+            })
+        );
+    }
+
     #[test]
     fn custom_caddy_server_opts() {
         // Use a TOML string here, because the toml! macro forces integers to fit i32
@@ -311,7 +892,7 @@ mod tests {
         );
         assert_eq!(
             parsed_config.caddy_server_opts.as_ref().unwrap().basic_auth,
-            Some(true)
+            Some(CaddyBasicAuthConfig::Enabled(true))
         );
         assert_eq!(
             parsed_config.caddy_server_opts.as_ref().unwrap().clean_urls,
@@ -402,6 +983,374 @@ mod tests {
         );
     }
 
+    #[test]
+    fn custom_security_headers() {
+        let toml_config = toml! {
+            security_headers = "strict"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.security_headers,
+            Some(SecurityHeadersPreset::Strict)
+        );
+    }
+
+    #[test]
+    fn custom_content_security_policy() {
+        let toml_config = toml! {
+            [content_security_policy]
+            policy = "default-src 'self'"
+            report_only = true
+            nonce = true
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.content_security_policy,
+            Some(ContentSecurityPolicyConfig {
+                policy: "default-src 'self'".to_string(),
+                report_only: Some(true),
+                nonce: Some(true),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_access() {
+        let toml_config = toml! {
+            [access]
+            allow = ["203.0.113.0/24", "198.51.100.7"]
+            allow_env = "ACCESS_ALLOW_IPS"
+            deny = ["203.0.113.13"]
+            deny_env = "ACCESS_DENY_IPS"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.access,
+            Some(AccessConfig {
+                allow: Some(vec![
+                    "203.0.113.0/24".to_string(),
+                    "198.51.100.7".to_string()
+                ]),
+                allow_env: Some("ACCESS_ALLOW_IPS".to_string()),
+                deny: Some(vec!["203.0.113.13".to_string()]),
+                deny_env: Some("ACCESS_DENY_IPS".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_forward_auth() {
+        let toml_config = toml! {
+            [forward_auth]
+            url_env = "FORWARD_AUTH_URL"
+            copy_headers = ["Remote-User", "Remote-Email"]
+            path_exclusions = ["/health"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.forward_auth,
+            Some(ForwardAuthConfig {
+                url_env: Some("FORWARD_AUTH_URL".to_string()),
+                copy_headers: Some(vec!["Remote-User".to_string(), "Remote-Email".to_string()]),
+                path_exclusions: Some(vec!["/health".to_string()]),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_maintenance() {
+        let toml_config = toml! {
+            [maintenance]
+            file_path = "maintenance.html"
+            status = 503
+            retry_after = 600
+            bypass_ips = ["203.0.113.0/24"]
+            bypass_cookie = "maintenance_bypass"
+            path_exclusions = ["/maintenance/*"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.maintenance,
+            Some(MaintenanceConfig {
+                file_path: Some(PathBuf::from("maintenance.html")),
+                status: Some(503),
+                retry_after: Some(600),
+                bypass_ips: Some(vec!["203.0.113.0/24".to_string()]),
+                bypass_cookie: Some("maintenance_bypass".to_string()),
+                path_exclusions: Some(vec!["/maintenance/*".to_string()]),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_cors() {
+        let toml_config = toml! {
+            [[cors]]
+            path_matcher = "/fonts/*"
+            allowed_origins = ["https://app.example.com"]
+            allowed_origins_env = "CORS_ALLOWED_ORIGINS"
+            allowed_methods = ["GET", "HEAD"]
+            allowed_headers = ["Content-Type"]
+            allow_credentials = true
+            max_age = 3600
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.cors,
+            Some(vec![CorsConfig {
+                path_matcher: "/fonts/*".to_string(),
+                allowed_origins: Some(vec!["https://app.example.com".to_string()]),
+                allowed_origins_env: Some("CORS_ALLOWED_ORIGINS".to_string()),
+                allowed_methods: Some(vec!["GET".to_string(), "HEAD".to_string()]),
+                allowed_headers: Some(vec!["Content-Type".to_string()]),
+                allow_credentials: Some(true),
+                max_age: Some(3600),
+            }])
+        );
+
+        let toml_config = toml! {
+            [cors]
+            path_matcher = "/fonts/*"
+            allowed_origins = ["*"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.cors,
+            Some(vec![CorsConfig {
+                path_matcher: "/fonts/*".to_string(),
+                allowed_origins: Some(vec!["*".to_string()]),
+                ..CorsConfig::default()
+            }])
+        );
+
+        let toml_config = toml! {
+            [cors]
+            allowed_origins = ["*"]
+        };
+        assert!(toml_config.try_into::<HerokuWebServerConfig>().is_err());
+    }
+
+    #[test]
+    fn custom_caching() {
+        let toml_config = toml! {
+            caching = "auto"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.caching, Some(CachingMode::Auto));
+
+        let toml_config = toml! {
+            caching = "forever"
+        };
+        assert!(toml_config.try_into::<HerokuWebServerConfig>().is_err());
+    }
+
+    #[test]
+    fn custom_precompress() {
+        let toml_config = toml! {
+            precompress = true
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.precompress, Some(true));
+    }
+
+    #[test]
+    fn custom_content_etags() {
+        let toml_config = toml! {
+            content_etags = false
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.content_etags, Some(false));
+    }
+
+    #[test]
+    fn custom_force_https() {
+        let toml_config = toml! {
+            [force_https]
+            status = 308
+            path_exclusions = ["/health"]
+
+            [force_https.hsts]
+            max_age = 63_072_000
+            include_subdomains = true
+            preload = true
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.force_https,
+            Some(ForceHttpsConfig {
+                status: Some(308),
+                path_exclusions: Some(vec!["/health".to_string()]),
+                hsts: Some(HstsConfig {
+                    max_age: Some(63_072_000),
+                    include_subdomains: Some(true),
+                    preload: Some(true),
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_canonical_host() {
+        let toml_config = toml! {
+            [canonical_host]
+            host_env = "CANONICAL_HOST"
+            status = 308
+            path_exclusions = ["/health"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.canonical_host,
+            Some(CanonicalHostConfig {
+                host: None,
+                host_env: Some("CANONICAL_HOST".to_string()),
+                status: Some(308),
+                path_exclusions: Some(vec!["/health".to_string()]),
+            })
+        );
+    }
+
+    #[test]
+    fn custom_trailing_slash() {
+        let toml_config = toml! {
+            trailing_slash = "never"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.trailing_slash, Some(TrailingSlashMode::Never));
+
+        let toml_config = toml! {
+            trailing_slash = "sometimes"
+        };
+        assert!(toml_config.try_into::<HerokuWebServerConfig>().is_err());
+    }
+
+    #[test]
+    fn custom_redirects() {
+        let toml_config = toml! {
+            [[redirects]]
+            from = "/blog/*"
+            to = "/posts/{1}"
+
+            [[redirects]]
+            from_regex = "^/docs/v(\\d+)/(.*)$"
+            to = "https://docs.example.com/{1}/{2}"
+            status = 302
+            preserve_query = true
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.root, None);
+        assert_eq!(parsed_config.headers, None);
+        assert_eq!(
+            parsed_config.redirects,
+            Some(vec![
+                RedirectConfig {
+                    from: Some("/blog/*".to_string()),
+                    from_regex: None,
+                    to: "/posts/{1}".to_string(),
+                    status: None,
+                    preserve_query: None,
+                },
+                RedirectConfig {
+                    from: None,
+                    from_regex: Some("^/docs/v(\\d+)/(.*)$".to_string()),
+                    to: "https://docs.example.com/{1}/{2}".to_string(),
+                    status: Some(302),
+                    preserve_query: Some(true),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn custom_rewrites() {
+        let toml_config = toml! {
+            [[rewrites]]
+            from = "/blog/*"
+            to = "/blog/post.html"
+
+            [[rewrites]]
+            from = "/app/*"
+            to = "/app/index.html"
+            if_target_exists = true
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.root, None);
+        assert_eq!(parsed_config.redirects, None);
+        assert_eq!(
+            parsed_config.rewrites,
+            Some(vec![
+                RewriteConfig {
+                    from: Some("/blog/*".to_string()),
+                    from_regex: None,
+                    to: "/blog/post.html".to_string(),
+                    if_target_exists: None,
+                },
+                RewriteConfig {
+                    from: Some("/app/*".to_string()),
+                    from_regex: None,
+                    to: "/app/index.html".to_string(),
+                    if_target_exists: Some(true),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn custom_proxies() {
+        let toml_config = toml! {
+            [[proxies]]
+            path_matcher = "/api/*"
+            upstream = "https://{env.API_HOST}"
+            strip_path_prefix = "/api"
+            host_header = "{http.reverse_proxy.upstream.host}"
+            dial_timeout = "5s"
+            response_header_timeout = "30s"
+
+            [[proxies]]
+            path_matcher = "/search"
+            upstream = "http://localhost:9200"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(parsed_config.root, None);
+        assert_eq!(parsed_config.headers, None);
+        assert_eq!(
+            parsed_config.proxies,
+            Some(vec![
+                ProxyConfig {
+                    path_matcher: "/api/*".to_string(),
+                    upstream: "https://{env.API_HOST}".to_string(),
+                    strip_path_prefix: Some("/api".to_string()),
+                    host_header: Some("{http.reverse_proxy.upstream.host}".to_string()),
+                    dial_timeout: Some("5s".to_string()),
+                    response_header_timeout: Some("30s".to_string()),
+                },
+                ProxyConfig {
+                    path_matcher: "/search".to_string(),
+                    upstream: "http://localhost:9200".to_string(),
+                    strip_path_prefix: None,
+                    host_header: None,
+                    dial_timeout: None,
+                    response_header_timeout: None,
+                },
+            ])
+        );
+    }
+
     #[test]
     fn custom_root() {
         let toml_config = toml! {
@@ -452,24 +1401,269 @@ mod tests {
                 PathMatchedHeader {
                     path_matcher: String::from("*"),
                     key: String::from("X-Global"),
-                    value: String::from("Hello")
+                    value: String::from("Hello"),
+                    ..PathMatchedHeader::default()
                 },
                 PathMatchedHeader {
                     path_matcher: String::from("/"),
                     key: String::from("X-Only-Default"),
-                    value: String::from("Hiii")
+                    value: String::from("Hiii"),
+                    ..PathMatchedHeader::default()
                 },
                 PathMatchedHeader {
                     path_matcher: String::from("*.html"),
                     key: String::from("X-Only-HTML"),
-                    value: String::from("Hi")
+                    value: String::from("Hi"),
+                    ..PathMatchedHeader::default()
                 },
                 PathMatchedHeader {
                     path_matcher: String::from("/images/*"),
                     key: String::from("X-Only-Images"),
-                    value: String::from("HAI")
+                    value: String::from("HAI"),
+                    ..PathMatchedHeader::default()
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn custom_header_operations() {
+        let toml_config = toml! {
+            [headers."*"]
+            X-Global = "Hello"
+            response.add.Link = ["</app.css>; rel=preload", "</app.js>; rel=preload"]
+            response.delete = ["Server"]
+            request.set.X-Forwarded-Prefix = "/docs"
+            request.delete = ["X-Debug"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.headers,
+            Some(vec![
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    key: String::from("X-Global"),
+                    value: String::from("Hello"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    target: HeaderTarget::Request,
+                    operation: HeaderOperation::Set,
+                    key: String::from("X-Forwarded-Prefix"),
+                    value: String::from("/docs"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    target: HeaderTarget::Request,
+                    operation: HeaderOperation::Delete,
+                    key: String::from("X-Debug"),
+                    value: String::new(),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    target: HeaderTarget::Response,
+                    operation: HeaderOperation::Add,
+                    key: String::from("Link"),
+                    value: String::from("</app.css>; rel=preload"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    target: HeaderTarget::Response,
+                    operation: HeaderOperation::Add,
+                    key: String::from("Link"),
+                    value: String::from("</app.js>; rel=preload"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    target: HeaderTarget::Response,
+                    operation: HeaderOperation::Delete,
+                    key: String::from("Server"),
+                    value: String::new(),
+                    ..PathMatchedHeader::default()
+                },
+            ])
+        );
+
+        let toml_config = toml! {
+            [headers."*"]
+            X-Global.set.X-Foo = "Bar"
+        };
+        assert!(toml_config.try_into::<HerokuWebServerConfig>().is_err());
+    }
+
+    #[test]
+    fn mounts_config() {
+        let toml_config = toml! {
+            [[mounts]]
+            path = "/docs/"
+            root = "docs/build"
+            index = "start.html"
+            spa_fallback.file_path = "start.html"
+            spa_fallback.path_exclusions = ["/assets/*"]
+            errors.404.file_path = "not-found.html"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.mounts,
+            Some(vec![MountConfig {
+                path: String::from("/docs/"),
+                root: PathBuf::from("docs/build"),
+                index: Some(String::from("start.html")),
+                spa_fallback: Some(SpaFallbackConfig::Options(SpaFallbackOpts {
+                    file_path: Some(PathBuf::from("start.html")),
+                    path_exclusions: Some(vec![String::from("/assets/*")]),
+                    ..SpaFallbackOpts::default()
+                })),
+                errors: Some(ErrorsConfig {
+                    custom_pages: BTreeMap::from([(
+                        404,
+                        ErrorConfig {
+                            file_path: PathBuf::from("not-found.html"),
+                            ..ErrorConfig::default()
+                        },
+                    )]),
+                }),
+            }])
+        );
+    }
+
+    #[test]
+    fn spa_fallback_config() {
+        let toml_config = toml! {
+            spa_fallback = false
+        };
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        let spa_fallback = parsed_config.spa_fallback.unwrap();
+        assert_eq!(spa_fallback, SpaFallbackConfig::Enabled(false));
+        assert!(!spa_fallback.is_enabled());
+
+        let toml_config = toml! {
+            [spa_fallback]
+            enabled = false
+            file_path = "index.html"
+        };
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert!(!parsed_config.spa_fallback.unwrap().is_enabled());
+
+        let toml_config = toml! {
+            [spa_fallback]
+            file_path = "index.html"
+        };
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert!(parsed_config.spa_fallback.unwrap().is_enabled());
+    }
+
+    #[test]
+    fn sites_config() {
+        let toml_config = toml! {
+            [[sites]]
+            hosts = ["docs.example.com"]
+            root = "sites/docs"
+            index = "home.html"
+            errors.404.file_path = "not-found.html"
+            headers."*".X-Site = "docs"
+
+            [[sites]]
+            hosts = ["blog.example.com", "news.example.com"]
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        let sites = parsed_config.sites.unwrap();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].hosts, vec!["docs.example.com"]);
+        assert_eq!(sites[0].root, Some(PathBuf::from("sites/docs")));
+        assert_eq!(sites[0].index, Some(String::from("home.html")));
+        assert_eq!(
+            sites[0]
+                .errors
+                .as_ref()
+                .and_then(|errors| errors.custom_pages.get(&404))
+                .map(|error| error.file_path.clone()),
+            Some(PathBuf::from("not-found.html"))
+        );
+        assert_eq!(
+            sites[0].headers,
+            Some(vec![PathMatchedHeader {
+                path_matcher: String::from("*"),
+                key: String::from("X-Site"),
+                value: String::from("docs"),
+                ..PathMatchedHeader::default()
+            }])
+        );
+        assert_eq!(sites[1].hosts, vec!["blog.example.com", "news.example.com"]);
+        assert_eq!(sites[1].root, None);
+    }
+
+    #[test]
+    fn custom_header_matchers() {
+        let toml_config = toml! {
+            [[headers."*"]]
+            match.host = ["www.example.com"]
+            X-Robots-Tag = "index"
+
+            [[headers."*"]]
+            match.content_type = ["text/html"]
+            match.status = [200, 404]
+            Cache-Control = "no-cache"
+        };
+
+        let parsed_config = toml_config.try_into::<HerokuWebServerConfig>().unwrap();
+        assert_eq!(
+            parsed_config.headers,
+            Some(vec![
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    matcher: HeaderMatcher {
+                        host: Some(vec![String::from("www.example.com")]),
+                        ..HeaderMatcher::default()
+                    },
+                    key: String::from("X-Robots-Tag"),
+                    value: String::from("index"),
+                    ..PathMatchedHeader::default()
+                },
+                PathMatchedHeader {
+                    path_matcher: String::from("*"),
+                    matcher: HeaderMatcher {
+                        content_type: Some(vec![String::from("text/html")]),
+                        status: Some(vec![200, 404]),
+                        ..HeaderMatcher::default()
+                    },
+                    key: String::from("Cache-Control"),
+                    value: String::from("no-cache"),
+                    ..PathMatchedHeader::default()
                 },
             ])
         );
     }
+
+    #[test]
+    fn custom_header_matchers_errors() {
+        let toml_config = toml! {
+            [headers."*"]
+            match.content_type = ["text/html"]
+            request.set.X-Html = "true"
+        };
+        let err = toml_config
+            .try_into::<HerokuWebServerConfig>()
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("request operations cannot match content_type or status"),
+            "{err}"
+        );
+
+        let toml_config = toml! {
+            [headers."*"]
+            match.status = [999]
+            X-Status = "unknown"
+        };
+        assert!(toml_config.try_into::<HerokuWebServerConfig>().is_err());
+    }
 }
@@ -0,0 +1,150 @@
+use crate::doc_root_files::list_doc_root_files;
+use base64::Engine;
+use html5ever::driver::ParseOpts;
+use html5ever::tendril::TendrilSink;
+use html5ever::{ns, parse_document};
+use markup5ever_rcdom::{Handle, NodeData, RcDom};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// Content-Security-Policy hash sources for the inline scripts & styles of an HTML document.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub(crate) struct InlineHashes {
+    pub(crate) scripts: BTreeSet<String>,
+    pub(crate) styles: BTreeSet<String>,
+}
+
+/// Parses each HTML file within the document root, returning the inline hashes of each,
+/// keyed by the URL path it is served at.
+pub(crate) fn find_inline_hashes(
+    doc_root: &Path,
+) -> Result<BTreeMap<String, InlineHashes>, std::io::Error> {
+    let mut result = BTreeMap::new();
+    for (path, url_path) in list_doc_root_files(doc_root) {
+        if is_html_file(&path) {
+            let html_bytes = fs::read(&path)?;
+            result.insert(
+                url_path,
+                html_inline_hashes(&String::from_utf8_lossy(&html_bytes)),
+            );
+        }
+    }
+    Ok(result)
+}
+
+pub(crate) fn is_html_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+}
+
+fn html_inline_hashes(html: &str) -> InlineHashes {
+    let dom = parse_document(RcDom::default(), ParseOpts::default()).one(html);
+    let mut inline_hashes = InlineHashes::default();
+    collect_inline_hashes(&dom.document, &mut inline_hashes);
+    inline_hashes
+}
+
+// Scripts with a `src` are not inline, so are covered by the policy's source list instead.
+fn collect_inline_hashes(node: &Handle, inline_hashes: &mut InlineHashes) {
+    if let NodeData::Element {
+        ref name,
+        ref attrs,
+        ..
+    } = node.data
+    {
+        if name.ns == ns!(html) {
+            let has_src = attrs.borrow().iter().any(|a| &*a.name.local == "src");
+            match &*name.local {
+                "script" if !has_src => {
+                    inline_hashes
+                        .scripts
+                        .insert(hash_source(&text_content(node)));
+                }
+                "style" => {
+                    inline_hashes
+                        .styles
+                        .insert(hash_source(&text_content(node)));
+                }
+                _ => {}
+            }
+        }
+    }
+    for child in node.children.borrow().iter() {
+        collect_inline_hashes(child, inline_hashes);
+    }
+}
+
+fn text_content(node: &Handle) -> String {
+    node.children
+        .borrow()
+        .iter()
+        .filter_map(|child| match child.data {
+            NodeData::Text { ref contents } => Some(contents.borrow().to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hash_source(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    format!(
+        "'sha256-{}'",
+        base64::engine::general_purpose::STANDARD.encode(digest)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_inline_scripts_and_styles() {
+        let inline_hashes = html_inline_hashes(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+  <style>body { color: black; }</style>
+  <script src="/assets/index.js"></script>
+  <script>alert('Hello, world.');</script>
+</head>
+<body>
+  <script>alert('Hello, world.');</script>
+</body>
+</html>"#,
+        );
+
+        assert_eq!(
+            inline_hashes,
+            InlineHashes {
+                scripts: BTreeSet::from([
+                    "'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='".to_string()
+                ]),
+                styles: BTreeSet::from([
+                    "'sha256-Br6tO8uuFyBAw2O0eUNdXVyuS/POLb5jpHxXaxIq6Q0='".to_string()
+                ]),
+            }
+        );
+    }
+
+    #[test]
+    fn hashes_nothing_without_inline_content() {
+        let inline_hashes = html_inline_hashes(
+            r#"<html><head><script src="/app.js"></script></head><body></body></html>"#,
+        );
+        assert_eq!(inline_hashes, InlineHashes::default());
+    }
+
+    #[test]
+    fn finds_inline_hashes_in_doc_root() {
+        let result = find_inline_hashes(Path::new("tests/fixtures/csp_hashes/public")).unwrap();
+        assert_eq!(
+            result.keys().collect::<Vec<_>>(),
+            vec!["/index.html", "/no-inline.html"]
+        );
+        assert_eq!(result["/index.html"].scripts.len(), 1);
+        assert!(result["/no-inline.html"].scripts.is_empty());
+    }
+}
@@ -1,9 +1,15 @@
+mod basic_auth_users;
 mod caddy_config;
 mod config_web_server;
+mod content_etags;
+mod csp_nonces;
+mod doc_root_files;
 mod errors;
 mod heroku_web_server_config;
+mod inline_hashes;
 mod install_web_server;
 mod o11y;
+mod precompress;
 
 use crate::errors::{on_error, StaticWebServerBuildpackError};
 use crate::o11y::*;
@@ -22,8 +28,8 @@ use libherokubuildpack::log::log_header;
 use semver::{Version, VersionReq};
 use sha2::Sha256;
 
+use bcrypt as _;
 use env_as_html_data as _;
-use regex as _;
 
 // Silence unused dependency warning for
 // dependencies only used in tests
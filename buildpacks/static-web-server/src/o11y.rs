@@ -23,12 +23,50 @@ pub(crate) const CONFIG_CADDY_SERVER_OPTS_ACCESS_LOGS: &str =
     formatcp!("{CONFIG}.caddy_server_opts_access_logs");
 pub(crate) const CONFIG_CADDY_SERVER_OPTS_STATIC_RESPONSES: &str =
     formatcp!("{CONFIG}.caddy_server_opts_static_responses");
+pub(crate) const CONFIG_CADDY_SERVER_OPTS_TRUSTED_PROXIES: &str =
+    formatcp!("{CONFIG}.caddy_server_opts_trusted_proxies");
 pub(crate) const CONFIG_DOC_ROOT_PATH: &str = formatcp!("{CONFIG}.doc_root_path");
 pub(crate) const CONFIG_DOC_INDEX: &str = formatcp!("{CONFIG}.doc_index");
 pub(crate) const CONFIG_BUILD_COMMAND: &str = formatcp!("{CONFIG}.build_command");
 pub(crate) const CONFIG_RUNTIME_CONFIG_ENABLED: &str = formatcp!("{CONFIG}.runtime_config_enabled");
 pub(crate) const CONFIG_ERROR_404_FILE_PATH: &str = formatcp!("{CONFIG}.error_404_file_path");
 pub(crate) const CONFIG_ERROR_404_STATUS_CODE: &str = formatcp!("{CONFIG}.error_404_status_code");
+pub(crate) const CONFIG_SECURITY_HEADERS: &str = formatcp!("{CONFIG}.security_headers");
+pub(crate) const CONFIG_CONTENT_SECURITY_POLICY_ENABLED: &str =
+    formatcp!("{CONFIG}.content_security_policy_enabled");
+pub(crate) const CONFIG_CONTENT_SECURITY_POLICY_HTML_FILES_COUNT: &str =
+    formatcp!("{CONFIG}.content_security_policy_html_files_count");
+pub(crate) const CONFIG_CONTENT_SECURITY_POLICY_NONCE_ENABLED: &str =
+    formatcp!("{CONFIG}.content_security_policy_nonce_enabled");
+pub(crate) const CONFIG_CONTENT_SECURITY_POLICY_NONCE_FILES_COUNT: &str =
+    formatcp!("{CONFIG}.content_security_policy_nonce_files_count");
+pub(crate) const CONFIG_ACCESS_ALLOW_ENABLED: &str = formatcp!("{CONFIG}.access_allow_enabled");
+pub(crate) const CONFIG_ACCESS_DENY_ENABLED: &str = formatcp!("{CONFIG}.access_deny_enabled");
+pub(crate) const CONFIG_FORWARD_AUTH_ENABLED: &str = formatcp!("{CONFIG}.forward_auth_enabled");
+pub(crate) const CONFIG_MAINTENANCE_ENABLED: &str = formatcp!("{CONFIG}.maintenance_enabled");
+pub(crate) const CONFIG_CORS_ENABLED: &str = formatcp!("{CONFIG}.cors_enabled");
+pub(crate) const CONFIG_CACHING_ENABLED: &str = formatcp!("{CONFIG}.caching_enabled");
+pub(crate) const CONFIG_CACHING_FINGERPRINTED_COUNT: &str =
+    formatcp!("{CONFIG}.caching_fingerprinted_count");
+pub(crate) const CONFIG_PRECOMPRESS_ENABLED: &str = formatcp!("{CONFIG}.precompress_enabled");
+pub(crate) const CONFIG_PRECOMPRESS_FILES_COUNT: &str =
+    formatcp!("{CONFIG}.precompress_files_count");
+pub(crate) const CONFIG_CONTENT_ETAGS_ENABLED: &str = formatcp!("{CONFIG}.content_etags_enabled");
+pub(crate) const CONFIG_CONTENT_ETAGS_FILES_COUNT: &str =
+    formatcp!("{CONFIG}.content_etags_files_count");
+pub(crate) const CONFIG_CANONICAL_HOST_ENABLED: &str = formatcp!("{CONFIG}.canonical_host_enabled");
+pub(crate) const CONFIG_FORCE_HTTPS_ENABLED: &str = formatcp!("{CONFIG}.force_https_enabled");
+pub(crate) const CONFIG_FORCE_HTTPS_HSTS_ENABLED: &str =
+    formatcp!("{CONFIG}.force_https_hsts_enabled");
+pub(crate) const CONFIG_TRAILING_SLASH: &str = formatcp!("{CONFIG}.trailing_slash");
+pub(crate) const CONFIG_REDIRECTS_ENABLED: &str = formatcp!("{CONFIG}.redirects_enabled");
+pub(crate) const CONFIG_REWRITES_ENABLED: &str = formatcp!("{CONFIG}.rewrites_enabled");
+pub(crate) const CONFIG_PROXIES_ENABLED: &str = formatcp!("{CONFIG}.proxies_enabled");
+pub(crate) const CONFIG_SPA_FALLBACK_ENABLED: &str = formatcp!("{CONFIG}.spa_fallback_enabled");
+pub(crate) const CONFIG_MOUNTS_COUNT: &str = formatcp!("{CONFIG}.mounts_count");
+pub(crate) const CONFIG_SITES_COUNT: &str = formatcp!("{CONFIG}.sites_count");
+pub(crate) const CONFIG_ERROR_PAGES_STATUS_CODES: &str =
+    formatcp!("{CONFIG}.error_pages_status_codes");
 pub(crate) const CONFIG_RESPONSE_HEADERS_ENABLED: &str =
     formatcp!("{CONFIG}.response_headers_enabled");
 
@@ -0,0 +1,140 @@
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Matches the default `minimum_length` of Caddy's encode handler,
+// below which compression overhead outweighs any savings.
+const MINIMUM_LENGTH: usize = 512;
+
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    "atom",
+    "css",
+    "csv",
+    "eot",
+    "htm",
+    "html",
+    "ico",
+    "js",
+    "json",
+    "map",
+    "md",
+    "mjs",
+    "otf",
+    "rss",
+    "svg",
+    "ttf",
+    "txt",
+    "wasm",
+    "webmanifest",
+    "xhtml",
+    "xml",
+];
+
+/// Writes `.br`, `.zst`, and `.gz` siblings for each compressible file, skipping any variant
+/// that is not smaller than the original. Returns how many files were precompressed.
+pub(crate) fn precompress_files(files: &[PathBuf]) -> Result<usize, std::io::Error> {
+    let mut count = 0;
+    for file in files.iter().filter(|f| is_compressible(f)) {
+        let contents = fs::read(file)?;
+        if contents.len() < MINIMUM_LENGTH {
+            continue;
+        }
+        let mut wrote_variant = false;
+        for (extension, compressed) in [
+            ("br", compress_brotli(&contents)?),
+            ("zst", zstd::encode_all(contents.as_slice(), 19)?),
+            ("gz", compress_gzip(&contents)?),
+        ] {
+            if compressed.len() < contents.len() {
+                fs::write(sibling_path(file, extension), compressed)?;
+                wrote_variant = true;
+            }
+        }
+        if wrote_variant {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn is_compressible(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| COMPRESSIBLE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut sibling = path.as_os_str().to_owned();
+    sibling.push(".");
+    sibling.push(extension);
+    PathBuf::from(sibling)
+}
+
+fn compress_brotli(contents: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut compressed = vec![];
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        encoder.write_all(contents)?;
+    }
+    Ok(compressed)
+}
+
+fn compress_gzip(contents: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+    let mut encoder = GzEncoder::new(vec![], flate2::Compression::best());
+    encoder.write_all(contents)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn detects_compressible_files() {
+        assert!(is_compressible(Path::new("public/index.html")));
+        assert!(is_compressible(Path::new(
+            "public/assets/index-B2x_9Abc.js"
+        )));
+        assert!(is_compressible(Path::new("public/STYLE.CSS")));
+        assert!(!is_compressible(Path::new("public/photo.jpg")));
+        assert!(!is_compressible(Path::new("public/fonts/font.woff2")));
+        assert!(!is_compressible(Path::new("public/index.html.gz")));
+        assert!(!is_compressible(Path::new("public/LICENSE")));
+    }
+
+    #[test]
+    fn writes_precompressed_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let large_file = dir.path().join("app.js");
+        let large_contents = "console.log('Hello, world!');\n".repeat(100);
+        fs::write(&large_file, &large_contents).unwrap();
+        let small_file = dir.path().join("small.js");
+        fs::write(&small_file, "console.log('Hi');").unwrap();
+        let image_file = dir.path().join("image.png");
+        fs::write(&image_file, large_contents.as_bytes()).unwrap();
+
+        let count = precompress_files(&[large_file, small_file, image_file]).unwrap();
+        assert_eq!(count, 1);
+
+        let mut decompressed = String::new();
+        brotli::Decompressor::new(fs::File::open(dir.path().join("app.js.br")).unwrap(), 4096)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, large_contents);
+
+        let decompressed =
+            zstd::decode_all(fs::File::open(dir.path().join("app.js.zst")).unwrap()).unwrap();
+        assert_eq!(String::from_utf8(decompressed).unwrap(), large_contents);
+
+        let mut decompressed = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(dir.path().join("app.js.gz")).unwrap())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, large_contents);
+
+        assert!(!dir.path().join("small.js.gz").exists());
+        assert!(!dir.path().join("image.png.gz").exists());
+    }
+}
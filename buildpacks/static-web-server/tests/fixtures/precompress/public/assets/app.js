@@ -0,0 +1,200 @@
+console.log("Precompressed line 0");
+console.log("Precompressed line 1");
+console.log("Precompressed line 2");
+console.log("Precompressed line 3");
+console.log("Precompressed line 4");
+console.log("Precompressed line 5");
+console.log("Precompressed line 6");
+console.log("Precompressed line 7");
+console.log("Precompressed line 8");
+console.log("Precompressed line 9");
+console.log("Precompressed line 10");
+console.log("Precompressed line 11");
+console.log("Precompressed line 12");
+console.log("Precompressed line 13");
+console.log("Precompressed line 14");
+console.log("Precompressed line 15");
+console.log("Precompressed line 16");
+console.log("Precompressed line 17");
+console.log("Precompressed line 18");
+console.log("Precompressed line 19");
+console.log("Precompressed line 20");
+console.log("Precompressed line 21");
+console.log("Precompressed line 22");
+console.log("Precompressed line 23");
+console.log("Precompressed line 24");
+console.log("Precompressed line 25");
+console.log("Precompressed line 26");
+console.log("Precompressed line 27");
+console.log("Precompressed line 28");
+console.log("Precompressed line 29");
+console.log("Precompressed line 30");
+console.log("Precompressed line 31");
+console.log("Precompressed line 32");
+console.log("Precompressed line 33");
+console.log("Precompressed line 34");
+console.log("Precompressed line 35");
+console.log("Precompressed line 36");
+console.log("Precompressed line 37");
+console.log("Precompressed line 38");
+console.log("Precompressed line 39");
+console.log("Precompressed line 40");
+console.log("Precompressed line 41");
+console.log("Precompressed line 42");
+console.log("Precompressed line 43");
+console.log("Precompressed line 44");
+console.log("Precompressed line 45");
+console.log("Precompressed line 46");
+console.log("Precompressed line 47");
+console.log("Precompressed line 48");
+console.log("Precompressed line 49");
+console.log("Precompressed line 50");
+console.log("Precompressed line 51");
+console.log("Precompressed line 52");
+console.log("Precompressed line 53");
+console.log("Precompressed line 54");
+console.log("Precompressed line 55");
+console.log("Precompressed line 56");
+console.log("Precompressed line 57");
+console.log("Precompressed line 58");
+console.log("Precompressed line 59");
+console.log("Precompressed line 60");
+console.log("Precompressed line 61");
+console.log("Precompressed line 62");
+console.log("Precompressed line 63");
+console.log("Precompressed line 64");
+console.log("Precompressed line 65");
+console.log("Precompressed line 66");
+console.log("Precompressed line 67");
+console.log("Precompressed line 68");
+console.log("Precompressed line 69");
+console.log("Precompressed line 70");
+console.log("Precompressed line 71");
+console.log("Precompressed line 72");
+console.log("Precompressed line 73");
+console.log("Precompressed line 74");
+console.log("Precompressed line 75");
+console.log("Precompressed line 76");
+console.log("Precompressed line 77");
+console.log("Precompressed line 78");
+console.log("Precompressed line 79");
+console.log("Precompressed line 80");
+console.log("Precompressed line 81");
+console.log("Precompressed line 82");
+console.log("Precompressed line 83");
+console.log("Precompressed line 84");
+console.log("Precompressed line 85");
+console.log("Precompressed line 86");
+console.log("Precompressed line 87");
+console.log("Precompressed line 88");
+console.log("Precompressed line 89");
+console.log("Precompressed line 90");
+console.log("Precompressed line 91");
+console.log("Precompressed line 92");
+console.log("Precompressed line 93");
+console.log("Precompressed line 94");
+console.log("Precompressed line 95");
+console.log("Precompressed line 96");
+console.log("Precompressed line 97");
+console.log("Precompressed line 98");
+console.log("Precompressed line 99");
+console.log("Precompressed line 100");
+console.log("Precompressed line 101");
+console.log("Precompressed line 102");
+console.log("Precompressed line 103");
+console.log("Precompressed line 104");
+console.log("Precompressed line 105");
+console.log("Precompressed line 106");
+console.log("Precompressed line 107");
+console.log("Precompressed line 108");
+console.log("Precompressed line 109");
+console.log("Precompressed line 110");
+console.log("Precompressed line 111");
+console.log("Precompressed line 112");
+console.log("Precompressed line 113");
+console.log("Precompressed line 114");
+console.log("Precompressed line 115");
+console.log("Precompressed line 116");
+console.log("Precompressed line 117");
+console.log("Precompressed line 118");
+console.log("Precompressed line 119");
+console.log("Precompressed line 120");
+console.log("Precompressed line 121");
+console.log("Precompressed line 122");
+console.log("Precompressed line 123");
+console.log("Precompressed line 124");
+console.log("Precompressed line 125");
+console.log("Precompressed line 126");
+console.log("Precompressed line 127");
+console.log("Precompressed line 128");
+console.log("Precompressed line 129");
+console.log("Precompressed line 130");
+console.log("Precompressed line 131");
+console.log("Precompressed line 132");
+console.log("Precompressed line 133");
+console.log("Precompressed line 134");
+console.log("Precompressed line 135");
+console.log("Precompressed line 136");
+console.log("Precompressed line 137");
+console.log("Precompressed line 138");
+console.log("Precompressed line 139");
+console.log("Precompressed line 140");
+console.log("Precompressed line 141");
+console.log("Precompressed line 142");
+console.log("Precompressed line 143");
+console.log("Precompressed line 144");
+console.log("Precompressed line 145");
+console.log("Precompressed line 146");
+console.log("Precompressed line 147");
+console.log("Precompressed line 148");
+console.log("Precompressed line 149");
+console.log("Precompressed line 150");
+console.log("Precompressed line 151");
+console.log("Precompressed line 152");
+console.log("Precompressed line 153");
+console.log("Precompressed line 154");
+console.log("Precompressed line 155");
+console.log("Precompressed line 156");
+console.log("Precompressed line 157");
+console.log("Precompressed line 158");
+console.log("Precompressed line 159");
+console.log("Precompressed line 160");
+console.log("Precompressed line 161");
+console.log("Precompressed line 162");
+console.log("Precompressed line 163");
+console.log("Precompressed line 164");
+console.log("Precompressed line 165");
+console.log("Precompressed line 166");
+console.log("Precompressed line 167");
+console.log("Precompressed line 168");
+console.log("Precompressed line 169");
+console.log("Precompressed line 170");
+console.log("Precompressed line 171");
+console.log("Precompressed line 172");
+console.log("Precompressed line 173");
+console.log("Precompressed line 174");
+console.log("Precompressed line 175");
+console.log("Precompressed line 176");
+console.log("Precompressed line 177");
+console.log("Precompressed line 178");
+console.log("Precompressed line 179");
+console.log("Precompressed line 180");
+console.log("Precompressed line 181");
+console.log("Precompressed line 182");
+console.log("Precompressed line 183");
+console.log("Precompressed line 184");
+console.log("Precompressed line 185");
+console.log("Precompressed line 186");
+console.log("Precompressed line 187");
+console.log("Precompressed line 188");
+console.log("Precompressed line 189");
+console.log("Precompressed line 190");
+console.log("Precompressed line 191");
+console.log("Precompressed line 192");
+console.log("Precompressed line 193");
+console.log("Precompressed line 194");
+console.log("Precompressed line 195");
+console.log("Precompressed line 196");
+console.log("Precompressed line 197");
+console.log("Precompressed line 198");
+console.log("Precompressed line 199");
@@ -59,10 +59,10 @@ impl Buildpack for WebsiteCreateReactAppBuildpack {
             .metadata(toml! {
                 root = "/workspace/build"
                 index = "index.html"
+                caching = "auto"
 
-                [errors.404]
+                [spa_fallback]
                 file_path = "index.html"
-                status = 200
                 path_exclusions = ["/static/*"]
             })
             .map_err(WebsiteCreateReactAppBuildpackError::SettingBuildPlanMetadata)?;
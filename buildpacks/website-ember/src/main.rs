@@ -60,10 +60,10 @@ impl Buildpack for WebsiteEmberBuildpack {
             .metadata(toml! {
                 root = "/workspace/dist"
                 index = "index.html"
+                caching = "auto"
 
-                [errors.404]
+                [spa_fallback]
                 file_path = "index.html"
-                status = 200
                 path_exclusions = ["/assets/*"]
             })
             .map_err(WebsiteEmberBuildpackError::SettingBuildPlanMetadata)?;
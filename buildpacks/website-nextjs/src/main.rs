@@ -64,6 +64,7 @@ impl Buildpack for WebsiteNextjsBuildpack {
             .metadata(toml! {
                 root = "/workspace/out"
                 index = "index.html"
+                caching = "auto"
 
                 [runtime_config]
                 html_files = ["**/*.html"]
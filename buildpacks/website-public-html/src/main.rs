@@ -65,6 +65,10 @@ impl Buildpack for WebsitePublicHTMLBuildpack {
         let mut metadata_table = toml::Table::new();
         metadata_table.insert("root".to_string(), toml::Value::String(root));
         metadata_table.insert("index".to_string(), toml::Value::String(index));
+        metadata_table.insert(
+            "caching".to_string(),
+            toml::Value::String("auto".to_string()),
+        );
         static_web_server_req
             .metadata(metadata_table)
             .map_err(WebsitePublicHTMLBuildpackError::SettingBuildPlanMetadata)?;
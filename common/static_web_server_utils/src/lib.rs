@@ -1,7 +1,14 @@
+use std::net::IpAddr;
 use std::path::Path;
+use std::sync::LazyLock;
 
 use libcnb::{read_toml_file, TomlFileError};
 use libherokubuildpack::toml::toml_select_value;
+use regex::Regex;
+
+static ENV_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("env name regex should compile")
+});
 
 pub fn read_project_config(dir: &Path) -> Result<Option<toml::Value>, TomlFileError> {
     let project_toml_path = dir.join("project.toml");
@@ -15,11 +22,32 @@ pub fn read_project_config(dir: &Path) -> Result<Option<toml::Value>, TomlFileEr
     Ok(project_config)
 }
 
+/// Whether the value is a valid environment variable name, to be read at launch.
+#[must_use]
+pub fn is_env_name(value: &str) -> bool {
+    ENV_NAME_REGEX.is_match(value)
+}
+
+/// Whether the value is an IP address, or a CIDR range like `10.0.0.0/8`.
+#[must_use]
+pub fn is_ip_range(value: &str) -> bool {
+    let (ip, prefix) = match value.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (value, None),
+    };
+    let max_prefix = match ip.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    prefix.is_none_or(|prefix| prefix.parse::<u8>().is_ok_and(|p| p <= max_prefix))
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
 
-    use crate::read_project_config;
+    use crate::{is_env_name, is_ip_range, read_project_config};
 
     #[test]
     fn reads_project_toml() {
@@ -48,4 +76,25 @@ mod tests {
         .unwrap();
         assert_eq!(project_config, None);
     }
+
+    #[test]
+    fn env_names() {
+        assert!(is_env_name("API_ORIGIN"));
+        assert!(is_env_name("_private1"));
+        assert!(!is_env_name("1PASSWORD"));
+        assert!(!is_env_name("API-ORIGIN"));
+        assert!(!is_env_name("API_ORIGIN}"));
+        assert!(!is_env_name(""));
+    }
+
+    #[test]
+    fn ip_ranges() {
+        assert!(is_ip_range("203.0.113.7"));
+        assert!(is_ip_range("203.0.113.0/24"));
+        assert!(is_ip_range("2001:db8::/32"));
+        assert!(!is_ip_range("203.0.113.0/33"));
+        assert!(!is_ip_range("2001:db8::/129"));
+        assert!(!is_ip_range("example.com"));
+        assert!(!is_ip_range("203.0.113.0/"));
+    }
 }